@@ -0,0 +1,57 @@
+//! Structured events for each access to a secret
+//!
+//! An [AuditSink] receives an [AuditEvent] after every request the client makes for a secret's
+//! data or metadata, whether it succeeded or not, including requests the client refused
+//! to send. Events never contain secret values.
+//! The sink is set with [Config::audit_sink](crate::config::Config::audit_sink).
+//! A failure to record an event is logged, and does not fail the operation.
+use std::{fmt, time::SystemTime};
+
+use async_trait::async_trait;
+
+/// Kind of access to a secret
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOperation {
+    Read,
+    Write,
+    Delete,
+    List,
+}
+
+/// Result of an access to a secret
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Success,
+    /// The request failed, with the error message
+    Failure(String),
+    /// The client refused to send the request, because of `read_only` or an open
+    /// circuit breaker, with the error message
+    Refused(String),
+}
+
+/// An access to a secret
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEvent {
+    pub operation: AuditOperation,
+    /// The kv mount
+    pub mount: String,
+    /// Path of the secret within the mount
+    pub path: String,
+    /// True if the secret's metadata was accessed, rather than its data
+    pub metadata: bool,
+    /// Time the request completed
+    pub timestamp: SystemTime,
+    /// `client_id` from the client's config
+    pub client_id: Option<String>,
+    /// Correlation id of the client view that made the request, if any
+    pub correlation_id: Option<String>,
+    pub outcome: AuditOutcome,
+}
+
+/// Destination of audit events
+#[async_trait]
+pub trait AuditSink: Send + Sync + fmt::Debug {
+    /// Records the event. Operations wait for this to return, so sinks that forward events
+    /// over the network should buffer them rather than send each one before returning.
+    async fn record(&self, event: AuditEvent) -> Result<(), String>;
+}
@@ -0,0 +1,45 @@
+//! Automatic decoding of base64 fields
+//!
+//! When enabled, top-level fields whose names end in [B64_SUFFIX] hold base64-encoded binary data.
+//! On read, each such field is decoded to an array of bytes, so it can be deserialized into a
+//! `Vec<u8>` field of the caller's type. On write, arrays of bytes in such fields are encoded again.
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::{Map, Value};
+
+/// Suffix of the names of base64-encoded fields
+pub const B64_SUFFIX: &str = "_b64";
+
+/// Decodes the base64 string in each `_b64` field of the map to an array of bytes.
+/// Returns the name of the first field that is not a valid base64 string.
+pub fn decode_fields(map: &mut Map<String, Value>) -> Result<(), String> {
+    for (field, value) in map
+        .iter_mut()
+        .filter(|(field, _)| field.ends_with(B64_SUFFIX))
+    {
+        let bytes = value
+            .as_str()
+            .and_then(|encoded| BASE64.decode(encoded).ok())
+            .ok_or_else(|| field.clone())?;
+        *value = Value::Array(bytes.into_iter().map(Value::from).collect());
+    }
+    Ok(())
+}
+
+/// Encodes each `_b64` field of the map holding an array of bytes as a base64 string.
+/// Fields that hold anything else, such as a string that is already encoded, are unchanged.
+pub fn encode_fields(map: &mut Map<String, Value>) {
+    for (_, value) in map
+        .iter_mut()
+        .filter(|(field, _)| field.ends_with(B64_SUFFIX))
+    {
+        let bytes = value.as_array().and_then(|items| {
+            items
+                .iter()
+                .map(|item| item.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect::<Option<Vec<u8>>>()
+        });
+        if let Some(bytes) = bytes {
+            *value = Value::String(BASE64.encode(bytes));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Circuit breaker for an unavailable vault server
+//!
+//! After a number of consecutive failed requests, the breaker opens and requests fail
+//! immediately with `VaultError::CircuitOpen` instead of waiting for the server.
+//! When the cooldown has elapsed, one request is allowed through as a probe:
+//! if it succeeds the breaker closes, and if it fails the breaker opens for another cooldown.
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::error::VaultError;
+
+#[derive(Debug)]
+enum State {
+    /// Requests are allowed. Holds the number of consecutive failures.
+    Closed(u32),
+    /// Requests are rejected until the instant
+    Open(Instant),
+    /// A probe request has been in flight since the instant, and other requests are rejected.
+    /// If the probe is dropped without a result, another probe is allowed after the cooldown.
+    Probing(Instant),
+}
+
+/// Tracks consecutive failures and rejects requests while the server is considered down
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    /// Creates a breaker that opens after `threshold` consecutive failures,
+    /// and stays open for `cooldown` before probing
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            threshold,
+            cooldown,
+            state: Mutex::new(State::Closed(0)),
+        }
+    }
+
+    /// Returns Ok if a request may be sent, or `VaultError::CircuitOpen` if it should fail now.
+    /// Each Ok must be followed by a call to [record](CircuitBreaker::record).
+    pub fn allow(&self) -> Result<(), VaultError> {
+        let mut state = self.state.lock().unwrap();
+        let until = match *state {
+            State::Closed(_) => return Ok(()),
+            State::Open(until) => until,
+            State::Probing(started) => started + self.cooldown,
+        };
+        let now = Instant::now();
+        if now >= until {
+            *state = State::Probing(now);
+            Ok(())
+        } else {
+            Err(VaultError::CircuitOpen {
+                retry_after: until - now,
+            })
+        }
+    }
+
+    /// Records the outcome of a request allowed by [allow](CircuitBreaker::allow).
+    /// `failed` should be true only if the server was unavailable.
+    pub fn record(&self, failed: bool) {
+        let mut state = self.state.lock().unwrap();
+        *state = match (&*state, failed) {
+            (_, false) => State::Closed(0),
+            (State::Closed(failures), true) if failures + 1 < self.threshold => {
+                State::Closed(failures + 1)
+            }
+            (_, true) => State::Open(Instant::now() + self.cooldown),
+        };
+    }
+}
@@ -0,0 +1,72 @@
+//! Splitting of data too large for one secret
+//!
+//! Vault limits the size of a single storage entry (1 MiB with the default integrated storage),
+//! so large data is stored as numbered chunks below a base path, with a manifest listing
+//! the checksum of each chunk and of the whole. Reading verifies both.
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Maximum bytes of data in one chunk. Chunks are stored base64-encoded,
+/// which adds a third, keeping each stored entry well under the limit.
+pub const CHUNK_BYTES: usize = 256 * 1024;
+
+/// Key of the manifest below the base path
+pub const MANIFEST_KEY: &str = "manifest";
+
+/// Returns the key of the numbered chunk below the base path
+pub fn chunk_key(index: usize) -> String {
+    format!("chunk-{}", index)
+}
+
+/// Describes the chunks of a large secret
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Total size of the data in bytes
+    pub size: usize,
+    /// Hex encoded sha256 of the whole data
+    pub sha256: String,
+    /// Hex encoded sha256 of each chunk, in order
+    pub chunks: Vec<String>,
+}
+
+/// Splits the data into chunks and returns them with their manifest
+pub fn split(data: &[u8]) -> (Manifest, Vec<&[u8]>) {
+    let chunks = data.chunks(CHUNK_BYTES).collect::<Vec<_>>();
+    let manifest = Manifest {
+        size: data.len(),
+        sha256: sha256_hex(data),
+        chunks: chunks.iter().map(|chunk| sha256_hex(chunk)).collect(),
+    };
+    (manifest, chunks)
+}
+
+/// Joins the chunks read for the manifest, where a chunk is None if it was not found.
+/// Returns a description of the problem if a chunk is missing or doesn't match its checksum.
+pub fn join(manifest: &Manifest, chunks: Vec<Option<Vec<u8>>>) -> Result<Vec<u8>, String> {
+    if chunks.len() != manifest.chunks.len() {
+        return Err(format!(
+            "expected {} chunks, got {}",
+            manifest.chunks.len(),
+            chunks.len()
+        ));
+    }
+    let mut data = Vec::with_capacity(manifest.size);
+    for (index, (chunk, expected)) in chunks.into_iter().zip(&manifest.chunks).enumerate() {
+        let chunk = chunk.ok_or_else(|| format!("{} is missing", chunk_key(index)))?;
+        if sha256_hex(&chunk) != *expected {
+            return Err(format!("{} does not match its checksum", chunk_key(index)));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    if data.len() != manifest.size || sha256_hex(&data) != manifest.sha256 {
+        return Err("data does not match the manifest checksum".to_string());
+    }
+    Ok(data)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
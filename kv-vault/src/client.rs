@@ -1,21 +1,383 @@
 //! Hashicorp vault client
 //!
-use std::{string::ToString, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    string::ToString,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
 
-use serde::{de::DeserializeOwned, Serialize};
-use vaultrs::api::kv2::responses::SecretVersionMetadata;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use futures::StreamExt;
+use opentelemetry::{
+    propagation::{Injector, TextMapPropagator},
+    sdk::propagation::TraceContextPropagator,
+};
+use reqwest::header::HeaderMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn, Instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use url::Url;
+use vaultrs::api::{
+    kv2::responses::{
+        ListSecretsResponse, ReadSecretMetadataResponse, ReadSecretResponse, SecretVersionMetadata,
+    },
+    EndpointError,
+};
 use vaultrs::client::{VaultClient, VaultClientSettings};
 
-use crate::{config::Config, error::VaultError};
+use crate::{
+    audit::{AuditEvent, AuditOperation, AuditOutcome, AuditSink},
+    b64,
+    breaker::CircuitBreaker,
+    chunk,
+    compression::{self, Codec},
+    config::{Config, TlsVersion},
+    diff::{self, FieldChange},
+    error::VaultError,
+    format::SecretFormat,
+    interceptor::ReadInterceptor,
+    path,
+    router::MountRouter,
+    schema,
+    secret::SecretString,
+    signer::RequestSigner,
+    tree, STRING_VALUE_MARKER,
+};
+
+/// Custom metadata field of the idempotency key of the latest write by [Client::write_secret_idempotent]
+pub const IDEMPOTENCY_KEY_FIELD: &str = "idempotency_key";
+
+/// Custom metadata fields recorded by writes when provenance stamping is enabled
+pub const WRITTEN_BY_FIELD: &str = "written_by";
+pub const WRITTEN_AT_FIELD: &str = "written_at";
+pub const CLIENT_ID_FIELD: &str = "client_id";
+
+/// Field of the envelope that secrets are wrapped in when `unwrap_data_envelope` is set
+pub const DATA_ENVELOPE_FIELD: &str = "data";
+
+/// Header carrying the correlation id set by [Client::with_correlation_id]
+pub const CORRELATION_ID_HEADER: &str = "X-Correlation-Id";
 
 /// Vault HTTP api version. As of Vault 1.9.x (Feb 2022), all http api calls use version 1
 const API_VERSION: u8 = 1;
 
+/// Vault release that added patching of kv v2 metadata, used by `stamp_provenance`
+const MIN_METADATA_PATCH_VERSION: semver::Version = semver::Version::new(1, 9, 0);
+
+/// Largest serialized secret that can be written. This is the default maximum entry size of
+/// vault's integrated storage; other storage backends may reject smaller secrets.
+pub const MAX_WRITE_BYTES: usize = 1024 * 1024;
+
+/// Number of attempts made by [Client::rotate_secret] and [Client::write_secret_ensuring]
+/// before giving up on a conflicting write
+pub const ROTATE_MAX_ATTEMPTS: u32 = 5;
+
+/// Part of the error vault returns for a token or credential whose validity period hasn't started,
+/// which usually means the clocks of the issuer and vault differ
+const NOT_YET_VALID_MESSAGE: &str = "not yet valid";
+
+/// Maximum number of redirects followed by one request, which is reqwest's default
+const MAX_REDIRECTS: usize = 10;
+
+/// Time that [Client::policies] and [Client::token_ttl] cache the token's lookup
+pub const POLICIES_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Token lookup for [Client::policies] and [Client::token_ttl], and when it was fetched
+type CachedLookup = Option<(Instant, TokenLookup)>;
+
+/// Maximum number of concurrent delete requests issued by [Client::delete_prefix]
+const DELETE_PREFIX_CONCURRENCY: usize = 8;
+
+/// Maximum number of concurrent reads issued by [Client::list_with_status], [Client::export],
+/// and [Client::secret_ages]
+const LIST_STATUS_CONCURRENCY: usize = 8;
+
+/// Initial and maximum delay between health checks in [Client::wait_until_unsealed]
+const UNSEAL_POLL_MIN: Duration = Duration::from_millis(250);
+const UNSEAL_POLL_MAX: Duration = Duration::from_secs(5);
+
+/// Error message returned by vault when the cas option of a write does not match the current version
+const CAS_MISMATCH_MESSAGE: &str = "check-and-set parameter did not match the current version";
+
+/// Header carrying vault's replicated state index, for read-after-write consistency
+const VAULT_INDEX_HEADER: &str = "X-Vault-Index";
+
+/// Body of a successful vault api response
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    data: T,
+}
+
+/// Data of a kv v2 read, with the metadata of the version read
+#[derive(Deserialize)]
+struct EntryResponse {
+    data: Value,
+    metadata: EntryMetadata,
+}
+
+#[derive(Deserialize)]
+struct EntryMetadata {
+    version: u64,
+    created_time: String,
+    #[serde(default)]
+    custom_metadata: Option<HashMap<String, String>>,
+}
+
+/// Fields of a token lookup used by the client
+#[derive(Clone, Deserialize)]
+struct TokenLookup {
+    #[serde(default)]
+    policies: Vec<String>,
+    /// Remaining lifetime of the token, in seconds, when it was looked up, or 0 if it doesn't expire
+    #[serde(default)]
+    ttl: u64,
+}
+
+/// Successful response from the vault http api
+struct RawResponse {
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl RawResponse {
+    fn json<T: DeserializeOwned>(&self) -> Result<T, VaultError> {
+        serde_json::from_slice(&self.body)
+            .map_err(|source| vaultrs::error::ClientError::JsonParseError { source }.into())
+    }
+}
+
+/// Collects trace context headers for a request
+struct HeaderInjector(Vec<(String, String)>);
+
+impl Injector for HeaderInjector {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.push((key.to_string(), value));
+    }
+}
+
+/// Engine-level settings of a kv v2 mount, which apply to every key in the mount
+/// unless overridden by the key's metadata
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KvMountConfig {
+    /// Maximum number of versions kept per key. 0 uses vault's default of 10.
+    pub max_versions: u64,
+    /// If true, all writes must include a check-and-set version
+    pub cas_required: bool,
+    /// Duration string (such as "30d") after which versions are deleted. "0s" disables deletion.
+    pub delete_version_after: String,
+}
+
+/// Metadata of the version created by a write
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct WriteMetadata {
+    /// The new version number
+    pub version: u64,
+    /// Time the version was created, in RFC 3339 format
+    pub created_time: String,
+    /// Custom metadata of the key, if any has been set
+    #[serde(default)]
+    pub custom_metadata: Option<HashMap<String, String>>,
+}
+
+/// Whether a write created a key or added a version to an existing key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteKind {
+    Created,
+    Updated,
+}
+
+impl WriteMetadata {
+    /// Returns whether the write created the key, which is when it wrote the first version.
+    /// A key whose metadata was deleted is created again by the next write.
+    pub fn kind(&self) -> WriteKind {
+        if self.version == 1 {
+            WriteKind::Created
+        } else {
+            WriteKind::Updated
+        }
+    }
+
+    /// Returns true if the write created the key
+    pub fn is_create(&self) -> bool {
+        self.kind() == WriteKind::Created
+    }
+}
+
+impl From<SecretVersionMetadata> for WriteMetadata {
+    fn from(metadata: SecretVersionMetadata) -> Self {
+        WriteMetadata {
+            version: metadata.version,
+            created_time: metadata.created_time,
+            custom_metadata: None,
+        }
+    }
+}
+
+/// Versions of the vault kv secrets engine
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KvVersion {
+    V1,
+    V2,
+}
+
+/// A secret's value with the metadata of the version it was read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecretEntry<D> {
+    pub data: D,
+    /// The version read
+    pub version: u64,
+    /// Time the version was created, in RFC 3339 format
+    pub created_time: String,
+    /// Custom metadata of the key. Servers before vault 1.9 don't return it with the data.
+    pub custom_metadata: Option<HashMap<String, String>>,
+}
+
+/// What [Client::read_secret_version] does when the requested version has been destroyed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnDestroyed {
+    /// Return `VaultError::VersionDestroyed`
+    #[default]
+    Error,
+    /// Read the latest version instead
+    FallbackLatest,
+}
+
+/// How [Client::list_secrets_with] post-processes the keys returned by vault,
+/// which are in no particular order. The default returns them as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ListOpts {
+    /// Sort the keys, ignoring the trailing '/' of folders, so a key sorts next to its folder
+    pub sort: bool,
+    /// Drop the folder `foo/` when the key `foo` is also listed, and any repeated keys.
+    /// The folder can still be listed by its path.
+    pub dedup: bool,
+}
+
+/// Metadata of a response-wrapping token
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct WrapInfo {
+    /// Api path of the request whose response was wrapped, such as `secret/data/app`
+    pub creation_path: String,
+    /// Time the token was created, in RFC 3339 format
+    pub creation_time: String,
+    /// Lifetime of the token, in seconds
+    pub creation_ttl: u64,
+}
+
+/// An enabled auth method
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct AuthMount {
+    /// Mount path of the method, such as `approle/`
+    #[serde(skip)]
+    pub path: String,
+    /// Type of the method, such as `approle` or `kubernetes`
+    #[serde(rename = "type")]
+    pub method_type: String,
+    /// Accessor of the mount, which identifies it in audit logs and identity aliases
+    pub accessor: String,
+}
+
+/// Whether a key's data can be read, derived from its metadata
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyStatus {
+    /// The latest version can be read
+    Active,
+    /// The latest version is soft-deleted or destroyed, and some older version is not destroyed
+    LatestDeleted,
+    /// Every version is destroyed, leaving only the key's metadata
+    AllDestroyed,
+}
+
+impl KeyStatus {
+    /// Determines the status from the key's metadata. A version with any deletion time
+    /// is treated as deleted, including one scheduled for deletion by `delete_version_after`.
+    pub fn from_metadata(metadata: &ReadSecretMetadataResponse) -> Self {
+        if metadata.versions.values().all(|v| v.destroyed) {
+            return KeyStatus::AllDestroyed;
+        }
+        match metadata.versions.get(&metadata.current_version.to_string()) {
+            Some(latest) if !latest.destroyed && latest.deletion_time.is_empty() => {
+                KeyStatus::Active
+            }
+            _ => KeyStatus::LatestDeleted,
+        }
+    }
+}
+
+/// Signed identity token issued by vault's identity secrets engine
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct IdentityToken {
+    /// The signed JWT
+    pub token: String,
+    /// Lifetime of the token, in seconds
+    pub ttl: u64,
+    /// Client id of the role, which downstream services see as the token's audience
+    pub client_id: String,
+}
+
+/// How secrets are removed by bulk deletes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeleteMode {
+    /// Soft-delete the latest version. Older versions are kept and the latest can be undeleted.
+    Soft,
+    /// Permanently remove all versions and metadata of the secret
+    Destroy,
+}
+
 /// Vault client connection information.
 #[derive(Clone)]
 pub struct Client {
-    inner: Arc<vaultrs::client::VaultClient>,
+    inner: Arc<VaultClient>,
+    replica: Option<Arc<VaultClient>>,
     namespace: String,
+    auto_decompress: bool,
+    compress_threshold: Option<usize>,
+    compression_codec: Codec,
+    auto_decode_b64_fields: bool,
+    unwrap_data_envelope: bool,
+    require_consistency: bool,
+    max_response_bytes: Option<usize>,
+    revoke_on_shutdown: bool,
+    request_signer: Option<Arc<dyn RequestSigner>>,
+    propagate_trace_context: bool,
+    router: Option<Arc<MountRouter>>,
+    breaker: Option<Arc<CircuitBreaker>>,
+    request_timeout: Option<Duration>,
+    success_status_codes: Arc<Vec<u16>>,
+    stamp_provenance: bool,
+    client_id: Option<String>,
+    read_only: bool,
+    pub(crate) transform_mount: String,
+    explain_permission_denied: bool,
+    read_interceptors: Arc<Vec<Arc<dyn ReadInterceptor>>>,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    /// Limits the number of concurrent requests of the client and its clones
+    limiter: Option<Arc<tokio::sync::Semaphore>>,
+    request_queue_timeout: Option<Duration>,
+    /// Number of requests in flight, shared by clones of the client
+    in_flight: Arc<AtomicUsize>,
+    /// `X-Vault-Index` returned by the most recent write, shared by clones of the client
+    last_index: Arc<Mutex<Option<String>>>,
+    /// Server version from the first call to [Client::server_version]
+    server_version: Arc<Mutex<Option<semver::Version>>>,
+    /// Kv engine versions from [Client::detect_kv_version], by mount
+    kv_version: Arc<Mutex<HashMap<String, KvVersion>>>,
+    /// The `kv_version` setting and the mount it applies to, if the version is not detected
+    configured_kv_version: Option<(String, KvVersion)>,
+    token_lookup: Arc<Mutex<CachedLookup>>,
+    /// Address of the active node that a standby redirected a write to, shared by clones of the client
+    active_addr: Arc<Mutex<Option<String>>>,
+    /// Vault Enterprise namespace sent with each request, set by [Client::scoped]
+    vault_namespace: Option<String>,
+    /// Correlation id sent with each request, set by [Client::with_correlation_id]
+    correlation_id: Option<String>,
 }
 
 impl Client {
@@ -24,64 +386,2653 @@ impl Client {
     /// Note that this constructor does not attempt to connect to the vault server,
     /// so the vault server does not need to be running at the time a LinkDefinition to this provider is created.
     pub fn new(config: Config) -> Result<Self, VaultError> {
-        Ok(Client {
-            inner: Arc::new(VaultClient::new(VaultClientSettings {
-                token: config.token,
-                address: config.addr,
-                ca_certs: config.certs,
-                verify: false,
-                version: API_VERSION,
-                wrapping: false,
-                timeout: None,
-            })?),
+        let replica = match &config.read_replica_addr {
+            Some(addr) => Some(Arc::new(vault_client(&config, addr.clone())?)),
+            None => None,
+        };
+        let inner = Arc::new(vault_client(&config, config.addr.clone())?);
+        Ok(Client::with_connections(config, inner, replica))
+    }
+
+    /// Creates a client using existing vault connections
+    pub(crate) fn with_connections(
+        config: Config,
+        inner: Arc<VaultClient>,
+        replica: Option<Arc<VaultClient>>,
+    ) -> Self {
+        let configured_kv_version = config
+            .kv_version
+            .map(|version| (config.mount.clone(), version));
+        Client {
+            inner,
+            replica,
             namespace: config.mount,
-        })
+            auto_decompress: config.auto_decompress,
+            compress_threshold: config.compress_threshold,
+            compression_codec: config.compression_codec,
+            auto_decode_b64_fields: config.auto_decode_b64_fields,
+            unwrap_data_envelope: config.unwrap_data_envelope,
+            require_consistency: config.require_consistency,
+            max_response_bytes: config.max_response_bytes,
+            revoke_on_shutdown: config.revoke_on_shutdown,
+            request_signer: config.request_signer,
+            propagate_trace_context: config.propagate_trace_context,
+            router: config.mount_router.map(Arc::new),
+            breaker: config.circuit_breaker_threshold.map(|threshold| {
+                Arc::new(CircuitBreaker::new(
+                    threshold,
+                    config.circuit_breaker_cooldown,
+                ))
+            }),
+            request_timeout: config.request_timeout,
+            success_status_codes: Arc::new(config.success_status_codes.unwrap_or_default()),
+            stamp_provenance: config.stamp_provenance,
+            client_id: config.client_id,
+            read_only: config.read_only,
+            transform_mount: config.transform_mount,
+            explain_permission_denied: config.explain_permission_denied,
+            read_interceptors: Arc::new(config.read_interceptors),
+            audit_sink: config.audit_sink,
+            limiter: config.max_concurrent_requests.map(|max| {
+                Arc::new(tokio::sync::Semaphore::new(
+                    max.min(tokio::sync::Semaphore::MAX_PERMITS),
+                ))
+            }),
+            request_queue_timeout: config.request_queue_timeout,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            last_index: Arc::new(Mutex::new(None)),
+            server_version: Arc::new(Mutex::new(None)),
+            kv_version: Arc::new(Mutex::new(HashMap::new())),
+            configured_kv_version,
+            token_lookup: Arc::new(Mutex::new(None)),
+            active_addr: Arc::new(Mutex::new(None)),
+            vault_namespace: None,
+            correlation_id: None,
+        }
+    }
+
+    /// Returns a view of the client whose requests are made in the vault enterprise namespace,
+    /// such as `team-a` or `team-a/app`, by sending it in the `X-Vault-Namespace` header.
+    /// An empty namespace is the root namespace. The view shares the client's connections
+    /// and settings, so it is cheap to create and can be dropped at any time.
+    pub fn scoped(&self, namespace: &str) -> Client {
+        let namespace = namespace.trim_matches('/');
+        Client {
+            vault_namespace: (!namespace.is_empty()).then(|| namespace.to_string()),
+            // kv versions are cached by mount name, which may differ between namespaces
+            kv_version: Arc::new(Mutex::new(HashMap::new())),
+            ..self.clone()
+        }
     }
 
-    /// Reads value of secret using namespace and key path
+    /// Returns a view of the client whose requests are tagged with a correlation id, so that all
+    /// the vault requests of one logical operation can be found together. The id is sent in the
+    /// [CORRELATION_ID_HEADER] header, and recorded in request spans and audit events.
+    /// If `id` is None, a new id is generated. Like [scoped](Client::scoped), the view is cheap.
+    pub fn with_correlation_id(&self, id: Option<&str>) -> Client {
+        Client {
+            correlation_id: Some(id.map(str::to_string).unwrap_or_else(new_correlation_id)),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the correlation id set by [with_correlation_id](Client::with_correlation_id)
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// Reads value of secret using namespace and key path.
+    /// If a read replica is configured and the primary is unavailable, the read is sent to the replica.
+    /// If a mount router is configured, the path is routed to its mount.
     pub async fn read_secret<D: DeserializeOwned>(&self, path: &str) -> Result<D, VaultError> {
-        match vaultrs::kv2::read(self.inner.as_ref(), &self.namespace, path).await {
-            Err(vaultrs::error::ClientError::APIError { code, errors: _ }) if code == 404 => {
-                Err(VaultError::NotFound {
+        let (client, path) = self.route(path)?;
+        let res = client.read_with_fallback(&path).await?;
+        client.decode(&path, res.data).await
+    }
+
+    /// Reads value of secret, returning None if the secret doesn't exist or its latest version
+    /// is deleted. Other errors are returned.
+    pub async fn read_secret_opt<D: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<Option<D>, VaultError> {
+        match self.read_secret(path).await {
+            Ok(value) => Ok(Some(value)),
+            Err(VaultError::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads value of secret, returning the default if the secret doesn't exist or its latest
+    /// version is deleted, as [read_secret_opt](Client::read_secret_opt) returns None.
+    /// Other errors are returned.
+    pub async fn read_secret_or<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        default: D,
+    ) -> Result<D, VaultError> {
+        Ok(self.read_secret_opt(path).await?.unwrap_or(default))
+    }
+
+    /// Returns the json bytes of the secret's data exactly as vault returned them,
+    /// with the original key order and number formatting. Use this instead of
+    /// [read_secret](Client::read_secret) when computing hashes or signatures over stored secrets,
+    /// since deserializing and reserializing can change the bytes.
+    /// Compressed secrets are returned compressed, and read interceptors are not applied.
+    pub async fn read_secret_canonical(&self, path: &str) -> Result<Vec<u8>, VaultError> {
+        #[derive(Deserialize)]
+        struct Canonical<'a> {
+            #[serde(borrow)]
+            data: &'a serde_json::value::RawValue,
+        }
+
+        let api_path = self.data_path(path).await?;
+        let res = self
+            .send(&self.inner, reqwest::Method::GET, &api_path, None)
+            .await
+            .map_err(|e| self.not_found(path, e))?;
+        let body: ApiResponse<Canonical> = serde_json::from_slice(&res.body)
+            .map_err(|source| vaultrs::error::ClientError::JsonParseError { source })?;
+        Ok(body.data.data.get().as_bytes().to_vec())
+    }
+
+    /// Reads value of secret, failing with `VaultError::SchemaMismatch` if the secret has fields
+    /// that `D` does not. The error also lists fields of `D` that the secret does not have,
+    /// which were filled by defaults. Fields are compared by serializing the value read
+    /// into `D` back to json, so `D` must serialize with the same field names it deserializes.
+    pub async fn read_secret_strict<D: DeserializeOwned + Serialize>(
+        &self,
+        path: &str,
+    ) -> Result<D, VaultError> {
+        let value = self.read_secret::<Value>(path).await?;
+        let parsed: D = serde_json::from_value(value.clone())
+            .map_err(|source| vaultrs::error::ClientError::JsonParseError { source })?;
+        let (extra, missing) = schema::field_diff(&value, &parsed);
+        if !extra.is_empty() {
+            return Err(VaultError::SchemaMismatch {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+                extra,
+                missing,
+            });
+        }
+        Ok(parsed)
+    }
+
+    /// Reads value of secret as `New`, or, if the secret can't be deserialized as `New`,
+    /// as `Old` converted with `migrate`. This lets readers accept secrets written with an
+    /// older schema until they are rewritten. If the secret is neither shape, the error
+    /// from deserializing it as `New` is returned.
+    pub async fn read_secret_migrate<Old, New, F>(
+        &self,
+        path: &str,
+        migrate: F,
+    ) -> Result<New, VaultError>
+    where
+        Old: DeserializeOwned,
+        New: DeserializeOwned,
+        F: FnOnce(Old) -> New,
+    {
+        let value = self.read_secret::<Value>(path).await?;
+        match serde_json::from_value::<New>(value.clone()) {
+            Ok(new) => Ok(new),
+            Err(source) => match serde_json::from_value::<Old>(value) {
+                Ok(old) => {
+                    debug!(path, "migrating secret from old schema");
+                    Ok(migrate(old))
+                }
+                Err(_) => Err(vaultrs::error::ClientError::JsonParseError { source }.into()),
+            },
+        }
+    }
+
+    /// Reads a secret whose shape is identified by a field of the secret, into the matching
+    /// variant of the enum `E`. `E` must be an internally tagged enum, annotated with
+    /// `#[serde(tag = "type")]` (or another field name), so that serde selects the variant
+    /// from that field. A secret with an unknown tag is a json parse error.
+    pub async fn read_tagged<E: DeserializeOwned>(&self, path: &str) -> Result<E, VaultError> {
+        self.read_secret(path).await
+    }
+
+    /// Reads value of secret, unless the token is cancelled first.
+    /// If the token is cancelled, the in-flight request is dropped, closing its connection,
+    /// and `VaultError::Cancelled` is returned.
+    pub async fn read_secret_cancellable<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        token: CancellationToken,
+    ) -> Result<D, VaultError> {
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(VaultError::Cancelled {
+                operation: format!("reading {}/{}", self.namespace, path),
+            }),
+            res = self.read_secret(path) => res,
+        }
+    }
+
+    /// Reads value of secret, and checks that it is at least the required version.
+    /// Returns `VaultError::StaleVersion` if the server, or the replica it was read from,
+    /// returns an older version, such as one read before a recent write was replicated.
+    pub async fn read_secret_min_version<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        min_version: u64,
+    ) -> Result<D, VaultError> {
+        let res = self.read_with_fallback(path).await?;
+        if res.metadata.version < min_version {
+            return Err(VaultError::StaleVersion {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+                available: res.metadata.version,
+                required: min_version,
+            });
+        }
+        self.decode(path, res.data).await
+    }
+
+    /// Reads value of secret, and returns it with the http headers of vault's response,
+    /// such as `X-Vault-Index`. This is intended for debugging proxies, caches, and replicas;
+    /// prefer [read_secret](Client::read_secret) for normal reads.
+    pub async fn read_secret_full<D: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<(D, HeaderMap), VaultError> {
+        let api_path = self.data_path(path).await?;
+        let res = self
+            .send(&self.inner, reqwest::Method::GET, &api_path, None)
+            .await
+            .map_err(|e| self.not_found(path, e))?;
+        let body: ApiResponse<ReadSecretResponse> = res.json()?;
+        Ok((self.decode(path, body.data.data).await?, res.headers))
+    }
+
+    /// Reads value of secret together with the metadata of the version read, from one response,
+    /// so that the version always matches the data. Caches can store the entry and compare its
+    /// version with [current_version](Client::current_version) to check whether it is stale.
+    /// Reads fall back to the replica and are routed as they are by [read_secret](Client::read_secret).
+    pub async fn read_entry<D: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<SecretEntry<D>, VaultError> {
+        let (client, path) = self.route(path)?;
+        let api_path = client.data_path(&path).await?;
+        let res = match (
+            client
+                .send(&client.inner, reqwest::Method::GET, &api_path, None)
+                .await,
+            &client.replica,
+        ) {
+            (Err(e), Some(replica)) if is_unavailable(&e) => {
+                warn!(error = %e, endpoint = %replica.settings.address, "primary vault read failed, reading from replica");
+                client
+                    .send(replica, reqwest::Method::GET, &api_path, None)
+                    .await
+            }
+            (res, _) => res,
+        }
+        .map_err(|e| client.not_found(&path, e))?;
+        client
+            .entry(&path, res.json::<ApiResponse<EntryResponse>>()?.data)
+            .await
+    }
+
+    /// Reads one version of the secret, with its metadata. If the version has been destroyed,
+    /// `on_destroyed` selects whether to return `VaultError::VersionDestroyed` or to read the
+    /// latest version instead. The returned flag is true if the latest version was read instead.
+    /// A soft-deleted version is NotFound, since it can still be undeleted.
+    pub async fn read_secret_version<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        version: u64,
+        on_destroyed: OnDestroyed,
+    ) -> Result<(SecretEntry<D>, bool), VaultError> {
+        let (client, path) = self.route(path)?;
+        match client.read_version(&path, version).await {
+            Err(VaultError::VersionDestroyed { .. })
+                if on_destroyed == OnDestroyed::FallbackLatest =>
+            {
+                debug!(%path, version, "version destroyed, reading latest version");
+                Ok((client.read_entry(&path).await?, true))
+            }
+            res => Ok((res?, false)),
+        }
+    }
+
+    /// Reads the secret repeatedly, every `poll`, until it has the named field, and returns it.
+    /// This handles secrets that are created before another process fills in all of their fields.
+    /// If `wait_for_secret` is true, a missing secret is polled like a missing field,
+    /// otherwise NotFound is returned immediately. Other errors, such as permission denied,
+    /// are always returned immediately.
+    /// Returns `VaultError::Timeout` if the field is still missing when the timeout elapses.
+    pub async fn read_until_field<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        field: &str,
+        timeout: Duration,
+        poll: Duration,
+        wait_for_secret: bool,
+    ) -> Result<D, VaultError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            match self.read_secret::<Value>(path).await {
+                Ok(value) if value.get(field).is_some() => {
+                    return serde_json::from_value(value).map_err(|source| {
+                        vaultrs::error::ClientError::JsonParseError { source }.into()
+                    })
+                }
+                Ok(_) => debug!(path, field, "waiting for field"),
+                Err(VaultError::NotFound { .. }) if wait_for_secret => {
+                    debug!(path, field, "waiting for secret")
+                }
+                Err(e) => return Err(e),
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(VaultError::Timeout {
+                    operation: format!(
+                        "waiting for field '{}' of {}/{}",
+                        field, self.namespace, path
+                    ),
+                });
+            }
+            tokio::time::sleep(poll.min(deadline - now)).await;
+        }
+    }
+
+    /// Returns the changes to the secret's fields from one version to another.
+    /// Values are redacted as hashes unless `include_values` is true; see [diff](crate::diff).
+    /// Returns `VaultError::VersionDestroyed` if either version has been destroyed.
+    pub async fn diff_versions(
+        &self,
+        path: &str,
+        from_version: u64,
+        to_version: u64,
+        include_values: bool,
+    ) -> Result<Vec<FieldChange>, VaultError> {
+        let from: SecretEntry<Value> = self.read_version(path, from_version).await?;
+        let to: SecretEntry<Value> = self.read_version(path, to_version).await?;
+        Ok(diff::diff_fields(&from.data, &to.data, include_values))
+    }
+
+    /// Reads one version of the secret, with its metadata
+    async fn read_version<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        version: u64,
+    ) -> Result<SecretEntry<D>, VaultError> {
+        let api_path = format!("{}?version={}", self.data_path(path).await?, version);
+        match self
+            .send(&self.inner, reqwest::Method::GET, &api_path, None)
+            .await
+            .map_err(|e| self.not_found(path, e))
+        {
+            Ok(res) => {
+                self.entry(path, res.json::<ApiResponse<EntryResponse>>()?.data)
+                    .await
+            }
+            Err(VaultError::NotFound { .. })
+                if self
+                    .kv_metadata(path)
+                    .await
+                    .ok()
+                    .and_then(|m| m.versions.get(&version.to_string()).map(|v| v.destroyed))
+                    .unwrap_or(false) =>
+            {
+                Err(VaultError::VersionDestroyed {
                     namespace: self.namespace.clone(),
                     path: path.to_string(),
+                    version,
                 })
             }
-            Err(e) => Err(e.into()),
-            Ok(val) => Ok(val),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads the field of the secret every `poll` until it equals the expected value, such as a
+    /// readiness flag written by another process. A missing secret or field, or a value that
+    /// doesn't deserialize into `D`, counts as not yet equal. Other errors are returned immediately.
+    /// Returns `VaultError::Timeout`, including the last value seen, if the deadline passes,
+    /// so this should only be used with fields that are not secret.
+    pub async fn wait_for_value<D: DeserializeOwned + PartialEq>(
+        &self,
+        path: &str,
+        field: &str,
+        expected: D,
+        timeout: Duration,
+        poll: Duration,
+    ) -> Result<(), VaultError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let last = match self.read_field::<Value>(path, field).await {
+                Ok(value) => {
+                    if serde_json::from_value::<D>(value.clone()).ok().as_ref() == Some(&expected) {
+                        return Ok(());
+                    }
+                    value.to_string()
+                }
+                Err(VaultError::NotFound { .. }) => "no secret".to_string(),
+                Err(VaultError::FieldNotFound { .. }) => "no field".to_string(),
+                Err(e) => return Err(e),
+            };
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(VaultError::Timeout {
+                    operation: format!(
+                        "waiting for field '{}' of {}/{} to have the expected value, last saw {}",
+                        field, self.namespace, path, last
+                    ),
+                });
+            }
+            debug!(path, field, "waiting for value");
+            tokio::time::sleep(poll.min(deadline - now)).await;
+        }
+    }
+
+    /// Reads the first of the paths that holds a secret, and returns the matching path with its value.
+    /// Paths are tried in order. NotFound errors move on to the next path, and any other error
+    /// is returned immediately. If none of the paths exist, the NotFound error of the last path is returned.
+    pub async fn read_first_present<D: DeserializeOwned>(
+        &self,
+        paths: &[&str],
+    ) -> Result<(String, D), VaultError> {
+        let mut not_found = VaultError::NotFound {
+            namespace: self.namespace.clone(),
+            path: String::new(),
+        };
+        for path in paths {
+            match self.read_secret(path).await {
+                Ok(value) => return Ok((path.to_string(), value)),
+                Err(e @ VaultError::NotFound { .. }) => not_found = e,
+                Err(e) => return Err(e),
+            }
         }
+        Err(not_found)
     }
 
-    /// Writes value of secret using namespace and key path
+    /// Writes value of secret using namespace and key path.
+    /// If a mount router is configured, the path is routed to its mount.
+    ///
+    /// If provenance stamping is enabled, the writer is then recorded in the key's custom metadata
+    /// under [WRITTEN_BY_FIELD], [WRITTEN_AT_FIELD] and [CLIENT_ID_FIELD]. Vault has no request
+    /// that writes data and metadata together, so this takes a second request, which patches
+    /// the metadata so that other custom metadata fields are kept.
     pub async fn write_secret<T: Serialize>(
         &self,
         path: &str,
         data: &T,
-    ) -> Result<SecretVersionMetadata, VaultError> {
-        vaultrs::kv2::set(self.inner.as_ref(), &self.namespace, path, data)
+    ) -> Result<WriteMetadata, VaultError> {
+        let (client, path) = self.route(path)?;
+        let data = client.encode(&path, data)?;
+        client.kv_write(&path, &data, None).await
+    }
+
+    /// Checks that the server can patch metadata, if `stamp_provenance` is set.
+    /// [kv_write](Client::kv_write) checks this before writing, so an older server fails the write
+    /// with `VaultError::UnsupportedByServer` instead of after the new version is written.
+    async fn require_provenance(&self) -> Result<(), VaultError> {
+        match self.stamp_provenance {
+            true => {
+                self.require_server_version("stamp_provenance", &MIN_METADATA_PATCH_VERSION)
+                    .await
+            }
+            false => Ok(()),
+        }
+    }
+
+    /// Merges the provenance of a write into the key's custom metadata
+    async fn stamp_provenance(
+        &self,
+        path: &str,
+        written: &WriteMetadata,
+    ) -> Result<(), VaultError> {
+        let mut custom = serde_json::Map::new();
+        custom.insert(
+            WRITTEN_BY_FIELD.to_string(),
+            Value::String(
+                concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_string(),
+            ),
+        );
+        custom.insert(
+            WRITTEN_AT_FIELD.to_string(),
+            Value::String(written.created_time.clone()),
+        );
+        if let Some(client_id) = &self.client_id {
+            custom.insert(
+                CLIENT_ID_FIELD.to_string(),
+                Value::String(client_id.clone()),
+            );
+        }
+        let api_path = self.metadata_path(path).await?;
+        let body = serde_json::json!({ "custom_metadata": custom });
+        self.send(&self.inner, reqwest::Method::PATCH, &api_path, Some(&body))
+            .await?;
+        Ok(())
+    }
+
+    /// Writes value of secret with check-and-set, for mounts with `cas_required` set, where
+    /// writes without a check-and-set version fail. The current version is read from the key's
+    /// metadata, or is 0 for a new key, and the write is checked against it. If another write
+    /// lands in between, such as when two writers both create a new key, the version is read
+    /// again and the write retried, up to [ROTATE_MAX_ATTEMPTS] attempts in all, so the
+    /// last write wins as it would without check-and-set.
+    pub async fn write_secret_ensuring<T: Serialize>(
+        &self,
+        path: &str,
+        data: &T,
+    ) -> Result<WriteMetadata, VaultError> {
+        let (client, path) = self.route(path)?;
+        let data = client.encode(&path, data)?;
+        let mut attempt = 1;
+        let written = loop {
+            let version = match client.kv_metadata(&path).await {
+                Ok(metadata) => metadata.current_version,
+                Err(VaultError::NotFound { .. }) => 0,
+                Err(e) => return Err(e),
+            };
+            match client.kv_write(&path, &data, Some(version)).await {
+                Ok(written) => break written,
+                Err(VaultError::CasMismatch { .. }) if attempt < ROTATE_MAX_ATTEMPTS => {
+                    debug!(path = %path, attempt, "secret changed before write, retrying");
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        Ok(written)
+    }
+
+    /// Writes value of secret, then reads back the version written and checks that it equals
+    /// the data, returning `VaultError::VerificationFailed` if it doesn't. This catches writes
+    /// altered on the way to vault, such as by a proxy, at the cost of an extra read. The
+    /// version returned by the write is read, so a concurrent write doesn't fail verification.
+    pub async fn write_secret_verified<T: Serialize + DeserializeOwned + PartialEq>(
+        &self,
+        path: &str,
+        data: &T,
+    ) -> Result<WriteMetadata, VaultError> {
+        let written = self.write_secret(path, data).await?;
+        let (client, path) = self.route(path)?;
+        let entry: SecretEntry<T> = client.read_version(&path, written.version).await?;
+        if &entry.data != data {
+            return Err(VaultError::VerificationFailed {
+                namespace: client.namespace.clone(),
+                path,
+                version: written.version,
+            });
+        }
+        Ok(written)
+    }
+
+    /// Checks that the value can be written as a secret, without sending it: it must serialize
+    /// to a json map, and be no larger than [MAX_WRITE_BYTES] after any compression.
+    /// Returns `VaultError::InvalidData` with the reason otherwise. Writes make the same check.
+    pub fn validate_write<T: Serialize>(&self, data: &T) -> Result<(), VaultError> {
+        self.encode("", data).map(|_| ())
+    }
+
+    /// Writes value of secret unless the latest version was written with the same idempotency key,
+    /// so that a retried write doesn't create another version. The key is stored in the
+    /// secret's custom metadata under [IDEMPOTENCY_KEY_FIELD]. If the write is skipped,
+    /// the metadata of the existing latest version is returned.
+    ///
+    /// The write uses check-and-set with the version that was checked, so a concurrent write
+    /// fails with `VaultError::CasMismatch` instead of being overwritten. The key is recorded
+    /// after the write, so if the write succeeds but recording the key fails, a retry writes again.
+    /// The key is merged into the custom metadata with a patch, so other fields are kept,
+    /// which requires vault 1.9 or later; `VaultError::UnsupportedByServer` is returned otherwise.
+    pub async fn write_secret_idempotent<T: Serialize>(
+        &self,
+        path: &str,
+        data: &T,
+        idempotency_key: &str,
+    ) -> Result<WriteMetadata, VaultError> {
+        #[derive(Deserialize)]
+        struct Metadata {
+            current_version: u64,
+            #[serde(default)]
+            custom_metadata: Option<HashMap<String, String>>,
+            versions: HashMap<String, vaultrs::api::kv2::responses::SecretMetadata>,
+        }
+
+        self.require_server_version("write_secret_idempotent", &MIN_METADATA_PATCH_VERSION)
+            .await?;
+        let api_path = self.metadata_path(path).await?;
+        let metadata = match self
+            .request::<ApiResponse<Metadata>>(reqwest::Method::GET, &api_path, None)
+            .await
+            .map_err(|e| self.not_found(path, e))
+        {
+            Ok(res) => Some(res.data),
+            Err(VaultError::NotFound { .. }) => None,
+            Err(e) => return Err(e),
+        };
+        let (mut custom, written) = match metadata {
+            Some(metadata) => {
+                let custom = metadata.custom_metadata.unwrap_or_default();
+                if custom.get(IDEMPOTENCY_KEY_FIELD).map(String::as_str) == Some(idempotency_key) {
+                    let created_time = metadata
+                        .versions
+                        .get(&metadata.current_version.to_string())
+                        .map(|v| v.created_time.clone())
+                        .unwrap_or_default();
+                    return Ok(WriteMetadata {
+                        version: metadata.current_version,
+                        created_time,
+                        custom_metadata: Some(custom),
+                    });
+                }
+                let data = self.encode(path, data)?;
+                let written = self
+                    .kv_write(path, &data, Some(metadata.current_version))
+                    .await?;
+                (custom, written)
+            }
+            None => {
+                let data = self.encode(path, data)?;
+                (HashMap::new(), self.kv_write(path, &data, Some(0)).await?)
+            }
+        };
+        // only the key is patched, so fields written by others since the metadata was read are kept
+        let body = serde_json::json!({
+            "custom_metadata": { IDEMPOTENCY_KEY_FIELD: idempotency_key },
+        });
+        self.send(&self.inner, reqwest::Method::PATCH, &api_path, Some(&body))
+            .await?;
+        custom.insert(
+            IDEMPOTENCY_KEY_FIELD.to_string(),
+            idempotency_key.to_string(),
+        );
+        Ok(WriteMetadata {
+            custom_metadata: Some(custom),
+            ..written
+        })
+    }
+
+    /// Reads value of secret, along with an opaque ETag identifying the version that was read.
+    /// The ETag can be passed to [write_if_etag_matches](Client::write_if_etag_matches)
+    /// to update the secret only if it has not changed since it was read.
+    pub async fn read_with_etag<D: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<(D, String), VaultError> {
+        let res = self.kv_read(&self.inner, path).await?;
+        let data = self.decode(path, res.data).await?;
+        Ok((data, format!("\"{}\"", res.metadata.version)))
+    }
+
+    /// Writes value of secret only if the current version matches the ETag returned
+    /// by [read_with_etag](Client::read_with_etag). Returns `VaultError::CasMismatch`
+    /// if the secret has been changed since the ETag was issued, or if the ETag
+    /// was not produced by this client.
+    pub async fn write_if_etag_matches<T: Serialize>(
+        &self,
+        path: &str,
+        data: &T,
+        etag: &str,
+    ) -> Result<WriteMetadata, VaultError> {
+        let cas = etag
+            .trim_matches('"')
+            .parse::<u64>()
+            .map_err(|_| VaultError::CasMismatch {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+            })?;
+        let data = self.encode(path, data)?;
+        self.kv_write(path, &data, Some(cas)).await
+    }
+
+    /// Replaces the secret with a value produced by `generate`, which is passed the current value,
+    /// or None if the secret doesn't exist or its latest version is deleted, and returns the new value.
+    /// The write uses check-and-set with the version that was read, so that concurrent rotations
+    /// don't overwrite each other. If another write wins, the read, generate, and write are retried,
+    /// up to [ROTATE_MAX_ATTEMPTS] attempts in all, after which `VaultError::CasMismatch` is returned.
+    pub async fn rotate_secret<D, F>(&self, path: &str, mut generate: F) -> Result<D, VaultError>
+    where
+        D: Serialize + DeserializeOwned,
+        F: FnMut(Option<D>) -> D,
+    {
+        let mut attempt = 1;
+        loop {
+            let (current, version) = match self.kv_read(&self.inner, path).await {
+                Ok(res) => (
+                    Some(self.decode(path, res.data).await?),
+                    res.metadata.version,
+                ),
+                Err(VaultError::NotFound { .. }) => match self.kv_metadata(path).await {
+                    Ok(metadata) => (None, metadata.current_version),
+                    Err(VaultError::NotFound { .. }) => (None, 0),
+                    Err(e) => return Err(e),
+                },
+                Err(e) => return Err(e),
+            };
+            let value = generate(current);
+            let data = self.encode(path, &value)?;
+            match self.kv_write(path, &data, Some(version)).await {
+                Ok(_) => return Ok(value),
+                Err(VaultError::CasMismatch { .. }) if attempt < ROTATE_MAX_ATTEMPTS => {
+                    debug!(path, attempt, "secret changed during rotation, retrying");
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Swaps the values of two existing secrets, such as the credentials of blue and green
+    /// deployments. Both are read, then each is written with the other's stored value using
+    /// check-and-set with the version read. Values are swapped as stored, without decoding them.
+    ///
+    /// Vault has no transactions across keys, so the swap is not atomic: readers may see both
+    /// secrets with the same value between the two writes. If the second write fails, the first
+    /// secret's previous value is written back. If a concurrent write causes a check-and-set
+    /// conflict, the swap is retried, up to [ROTATE_MAX_ATTEMPTS] attempts in all.
+    /// Returns `VaultError::SwapIncomplete` if the rollback fails too.
+    pub async fn swap_secrets(&self, path_a: &str, path_b: &str) -> Result<(), VaultError> {
+        let mut attempt = 1;
+        loop {
+            let a = self.kv_read(&self.inner, path_a).await?;
+            let b = self.kv_read(&self.inner, path_b).await?;
+            let res = match self
+                .kv_write(path_a, &b.data, Some(a.metadata.version))
+                .await
+            {
+                Ok(written) => {
+                    match self
+                        .kv_write(path_b, &a.data, Some(b.metadata.version))
+                        .await
+                    {
+                        Ok(_) => return Ok(()),
+                        Err(e) => {
+                            debug!(path = path_b, error = %e, "swap failed, rolling back");
+                            self.kv_write(path_a, &a.data, Some(written.version))
+                                .await
+                                .map_err(|rollback| VaultError::SwapIncomplete {
+                                    namespace: self.namespace.clone(),
+                                    path: path_a.to_string(),
+                                    version: written.version,
+                                    reason: rollback.to_string(),
+                                })?;
+                            Err(e)
+                        }
+                    }
+                }
+                Err(e) => Err(e),
+            };
+            match res {
+                Err(VaultError::CasMismatch { .. }) if attempt < ROTATE_MAX_ATTEMPTS => {
+                    debug!(
+                        path_a,
+                        path_b, attempt, "secret changed during swap, retrying"
+                    );
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Returns the current version number of the secret, from its metadata, without reading
+    /// the secret value. Callers can compare it to a cached version to decide whether to re-read.
+    pub async fn current_version(&self, path: &str) -> Result<u64, VaultError> {
+        Ok(self.kv_metadata(path).await?.current_version)
+    }
+
+    /// Returns the time since the latest version of each secret was created, from its metadata,
+    /// in the order of the paths, to find secrets that have not been rotated within a rotation
+    /// policy. [sample_secret_ages](Client::sample_secret_ages) samples this on an interval.
+    /// A failure to read one secret's metadata is returned in its entry.
+    pub async fn secret_ages(&self, paths: &[&str]) -> Vec<(String, Result<Duration, VaultError>)> {
+        // owned paths keep the stream Send when the sampler is spawned
+        let paths = paths
+            .iter()
+            .map(|path| path.to_string())
+            .collect::<Vec<_>>();
+        futures::stream::iter(paths)
+            .map(|path| async move {
+                let age = self.kv_metadata(&path).await.and_then(|metadata| {
+                    let created = metadata
+                        .versions
+                        .get(&metadata.current_version.to_string())
+                        .map(|v| v.created_time.as_str())
+                        .unwrap_or(metadata.updated_time.as_str());
+                    let created = humantime::parse_rfc3339_weak(created).map_err(|e| {
+                        VaultError::InvalidData {
+                            reason: format!("invalid created_time '{}': {}", created, e),
+                        }
+                    })?;
+                    Ok(SystemTime::now()
+                        .duration_since(created)
+                        .unwrap_or_default())
+                });
+                (path, age)
+            })
+            .buffered(LIST_STATUS_CONCURRENCY)
+            .collect()
             .await
-            .map_err(VaultError::from)
+    }
+
+    /// Samples the ages of the secrets with [secret_ages](Client::secret_ages) every `interval`,
+    /// until the token is cancelled. Each age is reported as a tracing event with the path and
+    /// a `secret_age_seconds` field, for log and trace pipelines to export as a gauge,
+    /// and a failure to read an age is logged as a warning.
+    pub async fn sample_secret_ages(
+        &self,
+        paths: &[String],
+        interval: Duration,
+        token: CancellationToken,
+    ) {
+        let paths = paths.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut ticks = tokio::time::interval(interval);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            let ages = tokio::select! {
+                biased;
+                _ = token.cancelled() => return,
+                ages = async {
+                    ticks.tick().await;
+                    self.secret_ages(&paths).await
+                } => ages,
+            };
+            for (path, age) in ages {
+                match age {
+                    Ok(age) => info!(%path, secret_age_seconds = age.as_secs(), "secret age"),
+                    Err(e) => warn!(%path, error = %e, "failed to sample secret age"),
+                }
+            }
+        }
     }
 
     /// Deletes the latest version of the secret. Note that if versions are in use, only the latest is deleted
     /// Returns Ok if the key was deleted, or Err for any other error including key not found
+    /// On a kv v1 mount, which has no versions, the secret is deleted.
     pub async fn delete_latest<T: Serialize>(&self, path: &str) -> Result<(), VaultError> {
-        vaultrs::kv2::delete_latest(self.inner.as_ref(), &self.namespace, path)
-            .await
-            .map_err(VaultError::from)
+        let api_path = match self.kv1_path(path).await? {
+            Some((_, api_path)) => api_path,
+            None => self.data_path(path).await?,
+        };
+        self.send(&self.inner, reqwest::Method::DELETE, &api_path, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the secret only if its current version is the expected version,
+    /// such as a version read earlier. Returns `VaultError::CasMismatch` if the secret has changed.
+    ///
+    /// With `DeleteMode::Soft`, the expected version is soft-deleted, so a version written
+    /// after the check is not deleted. With `DeleteMode::Destroy`, the key's metadata and all
+    /// versions are deleted, and a version written between the check and the delete is lost.
+    pub async fn delete_if_version(
+        &self,
+        path: &str,
+        expected_version: u64,
+        mode: DeleteMode,
+    ) -> Result<(), VaultError> {
+        if self.current_version(path).await? != expected_version {
+            return Err(VaultError::CasMismatch {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+            });
+        }
+        match mode {
+            DeleteMode::Soft => {
+                let api_path = self.secret_path("delete", path).await?;
+                let body = serde_json::json!({ "versions": [expected_version] });
+                self.send(&self.inner, reqwest::Method::POST, &api_path, Some(&body))
+                    .await?;
+                Ok(())
+            }
+            DeleteMode::Destroy => self.kv_delete_metadata(path).await,
+        }
     }
 
-    /// Lists keys at the path
+    /// Lists keys at the path.
+    /// If a read replica is configured and the primary is unavailable, the list is sent to the replica.
     pub async fn list_secrets(&self, path: &str) -> Result<Vec<String>, VaultError> {
-        match vaultrs::kv2::list(self.inner.as_ref(), &self.namespace, path).await {
-            Err(vaultrs::error::ClientError::APIError { code, errors: _ }) if code == 404 => {
-                Err(VaultError::NotFound {
-                    namespace: self.namespace.clone(),
-                    path: path.to_string(),
-                })
+        match (self.kv_list(&self.inner, path).await, &self.replica) {
+            (Err(e), Some(replica)) if is_unavailable(&e) => {
+                warn!(error = %e, endpoint = %replica.settings.address, "primary vault list failed, listing from replica");
+                self.kv_list(replica, path).await
             }
-            Err(e) => Err(e.into()),
-            Ok(secret_list) => Ok(secret_list),
+            (res, _) => res,
+        }
+    }
+
+    /// Lists keys at the path, returning an empty list if there are no keys under the path,
+    /// instead of the `VaultError::NotFound` returned by [list_secrets](Client::list_secrets).
+    pub async fn list_secrets_opt(&self, path: &str) -> Result<Vec<String>, VaultError> {
+        match self.list_secrets(path).await {
+            Err(VaultError::NotFound { .. }) => Ok(Vec::new()),
+            res => res,
+        }
+    }
+
+    /// Lists keys at the path like [list_secrets](Client::list_secrets),
+    /// then sorts and deduplicates them as selected by `opts`
+    pub async fn list_secrets_with(
+        &self,
+        path: &str,
+        opts: ListOpts,
+    ) -> Result<Vec<String>, VaultError> {
+        let mut keys = self.list_secrets(path).await?;
+        if opts.sort {
+            keys.sort_by(|a, b| {
+                (a.trim_end_matches('/'), a.len()).cmp(&(b.trim_end_matches('/'), b.len()))
+            });
         }
+        if opts.dedup {
+            let leaves = keys
+                .iter()
+                .filter(|key| !key.ends_with('/'))
+                .cloned()
+                .collect::<HashSet<_>>();
+            let mut seen = HashSet::new();
+            keys.retain(|key| {
+                let shadowed = key
+                    .strip_suffix('/')
+                    .map(|dir| leaves.contains(dir))
+                    .unwrap_or(false);
+                !shadowed && seen.insert(key.clone())
+            });
+        }
+        Ok(keys)
+    }
+
+    /// Lists keys at the path that match a glob pattern.
+    /// `*` matches any sequence of characters and `?` matches a single character.
+    /// Vault does not filter lists, so all keys are fetched and matched here.
+    /// Folder keys are matched without their trailing '/'.
+    pub async fn list_secrets_filtered(
+        &self,
+        path: &str,
+        pattern: &str,
+    ) -> Result<Vec<String>, VaultError> {
+        let keys = self.list_secrets(path).await?;
+        Ok(keys
+            .into_iter()
+            .filter(|key| glob_match(pattern, key.strip_suffix('/').unwrap_or(key)))
+            .collect())
     }
+
+    /// Lists the secrets at the path with the status of each, from reading each key's metadata.
+    /// Keys with children (those ending in '/') are not included.
+    /// This makes one request per key, so prefer [list_secrets](Client::list_secrets)
+    /// when the status isn't needed.
+    pub async fn list_with_status(
+        &self,
+        path: &str,
+    ) -> Result<Vec<(String, KeyStatus)>, VaultError> {
+        let dir = match path.trim_matches('/') {
+            "" => String::new(),
+            p => format!("{}/", p),
+        };
+        let keys = self.list_secrets(path).await?;
+        futures::stream::iter(keys.into_iter().filter(|key| !key.ends_with('/')))
+            .map(|key| {
+                let dir = &dir;
+                async move {
+                    let metadata = self.kv_metadata(&format!("{}{}", dir, key)).await?;
+                    Ok((key, KeyStatus::from_metadata(&metadata)))
+                }
+            })
+            .buffered(LIST_STATUS_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// Deletes every secret at or below the prefix, recursively, and returns the keys deleted.
+    /// If `dry_run` is true, nothing is deleted and the keys that would have been deleted are returned.
+    ///
+    /// Because this is destructive, an empty prefix (the entire mount) is rejected.
+    /// Deletes are issued with bounded concurrency. A failed delete doesn't stop the others;
+    /// if any fail, `VaultError::PartialDelete` reports how many keys were deleted,
+    /// and the key and error of each failure.
+    pub async fn delete_prefix(
+        &self,
+        prefix: &str,
+        mode: DeleteMode,
+        dry_run: bool,
+    ) -> Result<Vec<String>, VaultError> {
+        if prefix.trim_matches('/').is_empty() {
+            return Err(VaultError::InvalidPath {
+                path: prefix.to_string(),
+                reason: "delete_prefix requires a non-empty prefix".to_string(),
+            });
+        }
+        let keys = self.list_leaves(prefix).await?;
+        if dry_run {
+            return Ok(keys);
+        }
+
+        let mut results = futures::stream::iter(keys)
+            .map(|key| async move {
+                let res = match mode {
+                    DeleteMode::Soft => self.delete_latest::<Value>(&key).await,
+                    DeleteMode::Destroy => self.kv_delete_metadata(&key).await,
+                };
+                (key, res)
+            })
+            .buffer_unordered(DELETE_PREFIX_CONCURRENCY);
+
+        let mut deleted = Vec::new();
+        let mut errors = Vec::new();
+        while let Some((key, res)) = results.next().await {
+            match res {
+                Ok(()) => deleted.push(key),
+                Err(e) => errors.push((key, e)),
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(deleted),
+            false => Err(VaultError::PartialDelete {
+                deleted: deleted.len(),
+                errors,
+            }),
+        }
+    }
+
+    /// Writes each secret in a nested json tree at its path below the base path,
+    /// and returns the number of secrets written. See [tree](crate::tree) for how secrets
+    /// and directories are told apart.
+    ///
+    /// If `overwrite` is false, secrets that already exist are skipped, using check-and-set
+    /// so that a key created concurrently is not overwritten either. At most `concurrency`
+    /// writes are in flight at once. If a write fails, no further writes are started and
+    /// the error is returned; secrets already written are kept.
+    pub async fn import(
+        &self,
+        tree: &Value,
+        base_path: &str,
+        overwrite: bool,
+        concurrency: usize,
+    ) -> Result<usize, VaultError> {
+        let failed = AtomicBool::new(false);
+        let mut results = futures::stream::iter(tree::flatten(tree, base_path))
+            .map(|(path, secret)| {
+                let failed = &failed;
+                async move {
+                    if failed.load(Ordering::SeqCst) {
+                        return None;
+                    }
+                    let res = self.import_secret(&path, &secret, overwrite).await;
+                    if res.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    Some(res)
+                }
+            })
+            .buffer_unordered(concurrency.max(1));
+
+        let mut written = 0;
+        let mut error = None;
+        while let Some(res) = results.next().await {
+            match res {
+                Some(Ok(true)) => written += 1,
+                Some(Err(e)) if error.is_none() => error = Some(e),
+                _ => {}
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(written),
+        }
+    }
+
+    /// Writes one imported secret, returning false if it was skipped because it exists
+    async fn import_secret(
+        &self,
+        path: &str,
+        secret: &Value,
+        overwrite: bool,
+    ) -> Result<bool, VaultError> {
+        let (client, path) = self.route(path)?;
+        let data = client.encode(&path, secret)?;
+        let cas = if overwrite { None } else { Some(0) };
+        match client.kv_write(&path, &data, cas).await {
+            Ok(_) => Ok(true),
+            Err(VaultError::CasMismatch { .. }) if !overwrite => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads every secret below the base path into a nested json tree that
+    /// [import](Client::import) can write back. Keys whose latest version is deleted are skipped,
+    /// as is a secret at the base path itself.
+    pub async fn export(&self, base_path: &str) -> Result<Value, VaultError> {
+        let base = base_path.trim_matches('/');
+        let keys = self
+            .list_leaves(base)
+            .await?
+            .into_iter()
+            .filter_map(|key| {
+                let key = key.trim_start_matches('/');
+                match base {
+                    "" => Some(key.to_string()),
+                    _ => key
+                        .strip_prefix(base)
+                        .and_then(|rest| rest.strip_prefix('/'))
+                        .map(str::to_string),
+                }
+            })
+            .collect::<Vec<_>>();
+        let secrets = futures::stream::iter(keys)
+            .map(|key| async move {
+                let path = match base {
+                    "" => key.clone(),
+                    _ => format!("{}/{}", base, key),
+                };
+                match self.read_secret::<Value>(&path).await {
+                    Ok(secret) => Ok(Some((key, secret))),
+                    Err(VaultError::NotFound { .. }) => Ok(None),
+                    Err(e) => Err(e),
+                }
+            })
+            .buffered(LIST_STATUS_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        let mut tree = Value::Object(serde_json::Map::new());
+        for secret in secrets {
+            if let Some((key, secret)) = secret? {
+                tree::insert(&mut tree, &key, secret);
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Returns the keys of all secrets at or below the prefix
+    async fn list_leaves(&self, prefix: &str) -> Result<Vec<String>, VaultError> {
+        let mut leaves = Vec::new();
+        let mut dirs = vec![format!("{}/", prefix.trim_matches('/'))];
+        while let Some(dir) = dirs.pop() {
+            let entries = match self.list_secrets(&dir).await {
+                Ok(entries) => entries,
+                Err(VaultError::NotFound { .. }) => continue,
+                Err(e) => return Err(e),
+            };
+            for entry in entries {
+                if entry.ends_with('/') {
+                    dirs.push(format!("{}{}", dir, entry));
+                } else {
+                    leaves.push(format!("{}{}", dir, entry));
+                }
+            }
+        }
+        // the prefix itself may also be a secret
+        let key = prefix.trim_matches('/');
+        if !key.is_empty() {
+            match self.kv_metadata(key).await {
+                Ok(_) => leaves.push(key.to_string()),
+                Err(VaultError::NotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        leaves.sort();
+        Ok(leaves)
+    }
+
+    /// Reads a secret stored as text in the [STRING_VALUE_MARKER](crate::STRING_VALUE_MARKER) field,
+    /// and parses the text in the format.
+    /// Returns `VaultError::FieldNotFound` if the field is missing,
+    /// or `VaultError::InvalidFormat` if the text can't be parsed.
+    pub async fn read_secret_as<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        format: SecretFormat,
+    ) -> Result<D, VaultError> {
+        let mut map = self
+            .read_secret::<serde_json::Map<String, Value>>(path)
+            .await?;
+        let invalid = |reason| VaultError::InvalidFormat {
+            namespace: self.namespace.clone(),
+            path: path.to_string(),
+            format,
+            reason,
+        };
+        match map.remove(STRING_VALUE_MARKER) {
+            Some(Value::String(text)) => format.parse(&text).map_err(invalid),
+            Some(_) => Err(invalid("field is not a string".to_string())),
+            None => Err(VaultError::FieldNotFound {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+                field: STRING_VALUE_MARKER.to_string(),
+            }),
+        }
+    }
+
+    /// Writes the data as text in the format, stored in the
+    /// [STRING_VALUE_MARKER](crate::STRING_VALUE_MARKER) field, so that it can be read back
+    /// with [read_secret_as](Client::read_secret_as).
+    pub async fn write_secret_as<T: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        data: &T,
+        format: SecretFormat,
+    ) -> Result<WriteMetadata, VaultError> {
+        let text = format
+            .render(data)
+            .map_err(|reason| VaultError::InvalidFormat {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+                format,
+                reason,
+            })?;
+        self.write_secret(path, &serde_json::json!({ STRING_VALUE_MARKER: text }))
+            .await
+    }
+
+    /// Reads binary data stored as a base64-encoded string in one field of the secret.
+    /// Returns `VaultError::FieldNotFound` if the field is missing,
+    /// or `VaultError::InvalidBase64` if it does not contain a base64 string.
+    pub async fn read_blob(&self, path: &str, field: &str) -> Result<Vec<u8>, VaultError> {
+        let mut map = self
+            .read_secret::<serde_json::Map<String, Value>>(path)
+            .await?;
+        let invalid = || VaultError::InvalidBase64 {
+            namespace: self.namespace.clone(),
+            path: path.to_string(),
+            field: field.to_string(),
+        };
+        match map.remove(field) {
+            Some(Value::String(encoded)) => BASE64.decode(encoded).map_err(|_| invalid()),
+            Some(_) => Err(invalid()),
+            None => Err(VaultError::FieldNotFound {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+                field: field.to_string(),
+            }),
+        }
+    }
+
+    /// Writes data of any size as chunks below the base path, at `chunk-0` to `chunk-N`,
+    /// followed by a `manifest` with the checksum of each chunk. See [chunk](crate::chunk).
+    /// The manifest is written last, so an interrupted write leaves the previous manifest in place,
+    /// which then fails its checksum when read. Chunks left over from earlier, larger writes are not deleted.
+    pub async fn write_large(
+        &self,
+        base_path: &str,
+        bytes: &[u8],
+    ) -> Result<WriteMetadata, VaultError> {
+        let base = path::normalize(base_path)?;
+        let (manifest, chunks) = chunk::split(bytes);
+        for (index, data) in chunks.into_iter().enumerate() {
+            let key = format!("{}/{}", base, chunk::chunk_key(index));
+            self.write_blob(&key, "data", data).await?;
+        }
+        let key = format!("{}/{}", base, chunk::MANIFEST_KEY);
+        self.write_secret(&key, &manifest).await
+    }
+
+    /// Reads data written by [write_large](Client::write_large), checking each chunk against the manifest.
+    /// Returns `VaultError::InvalidChunkedData` if a chunk is missing or doesn't match its checksum.
+    pub async fn read_large(&self, base_path: &str) -> Result<Vec<u8>, VaultError> {
+        let base = path::normalize(base_path)?;
+        let manifest: chunk::Manifest = self
+            .read_secret(&format!("{}/{}", base, chunk::MANIFEST_KEY))
+            .await?;
+        let mut chunks = Vec::with_capacity(manifest.chunks.len());
+        for index in 0..manifest.chunks.len() {
+            let key = format!("{}/{}", base, chunk::chunk_key(index));
+            match self.read_blob(&key, "data").await {
+                Ok(data) => chunks.push(Some(data)),
+                Err(VaultError::NotFound { .. } | VaultError::FieldNotFound { .. }) => {
+                    chunks.push(None)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        chunk::join(&manifest, chunks).map_err(|reason| VaultError::InvalidChunkedData {
+            namespace: self.namespace.clone(),
+            path: base,
+            reason,
+        })
+    }
+
+    /// Reads one field of the secret as a [SecretString], which is redacted when formatted.
+    /// Fields that are not strings are returned as their json text.
+    /// Returns `VaultError::FieldNotFound` if the field is missing.
+    pub async fn read_string_secret(
+        &self,
+        path: &str,
+        field: &str,
+    ) -> Result<SecretString, VaultError> {
+        let mut map = self
+            .read_secret::<serde_json::Map<String, Value>>(path)
+            .await?;
+        match map.remove(field) {
+            Some(Value::String(value)) => Ok(SecretString::new(value)),
+            Some(value) => Ok(SecretString::new(value.to_string())),
+            None => Err(VaultError::FieldNotFound {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+                field: field.to_string(),
+            }),
+        }
+    }
+
+    /// Reads one field of the secret into `D`. The field may be a dotted path to a nested field,
+    /// such as `db.password`, where numeric segments index into arrays. A top-level field whose
+    /// name contains dots is matched first. Returns `VaultError::FieldNotFound` if the field is missing.
+    pub async fn read_field<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        field: &str,
+    ) -> Result<D, VaultError> {
+        let mut secret = self.read_secret::<Value>(path).await?;
+        let value = match secret.get_mut(field) {
+            Some(value) => Some(value.take()),
+            None => field
+                .split('.')
+                .try_fold(&mut secret, |value, segment| match value {
+                    Value::Array(items) => {
+                        segment.parse::<usize>().ok().and_then(|i| items.get_mut(i))
+                    }
+                    value => value.get_mut(segment),
+                })
+                .map(Value::take),
+        };
+        let value = value.ok_or_else(|| VaultError::FieldNotFound {
+            namespace: self.namespace.clone(),
+            path: path.to_string(),
+            field: field.to_string(),
+        })?;
+        Ok(serde_json::from_value(value)
+            .map_err(|source| vaultrs::error::ClientError::JsonParseError { source })?)
+    }
+
+    /// Writes binary data as a base64-encoded string in one field of the secret.
+    /// The new version of the secret contains only that field.
+    pub async fn write_blob(
+        &self,
+        path: &str,
+        field: &str,
+        bytes: &[u8],
+    ) -> Result<WriteMetadata, VaultError> {
+        let mut map = serde_json::Map::new();
+        map.insert(field.to_string(), Value::String(BASE64.encode(bytes)));
+        self.write_secret(path, &map).await
+    }
+
+    /// Reads the engine-level configuration of the mount.
+    /// Returns `VaultError::PermissionDenied` if the token may not read the mount config.
+    pub async fn read_mount_config(&self) -> Result<KvMountConfig, VaultError> {
+        let api_path = format!("{}/config", self.namespace);
+        match self
+            .request::<ApiResponse<KvMountConfig>>(reqwest::Method::GET, &api_path, None)
+            .await
+        {
+            Ok(res) => Ok(res.data),
+            Err(e) => Err(self.permission_denied("config", e)),
+        }
+    }
+
+    /// Updates the engine-level configuration of the mount.
+    /// Returns `VaultError::PermissionDenied` if the token may not write the mount config.
+    pub async fn write_mount_config(&self, config: &KvMountConfig) -> Result<(), VaultError> {
+        let api_path = format!("{}/config", self.namespace);
+        let body = serde_json::to_value(config)
+            .map_err(|source| vaultrs::error::ClientError::JsonParseError { source })?;
+        match self
+            .send(&self.inner, reqwest::Method::POST, &api_path, Some(&body))
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(self.permission_denied("config", e)),
+        }
+    }
+
+    /// Waits until the vault server reports that it is unsealed, polling its health endpoint
+    /// with increasing delay. Returns `VaultError::Timeout` if the server is still sealed,
+    /// uninitialized, or unreachable when the timeout elapses.
+    ///
+    /// Health checks bypass the circuit breaker, so an open breaker doesn't hide an unsealed
+    /// server, and ignore `success_status_codes`, so a sealed server's 503 is never a success.
+    pub async fn wait_until_unsealed(&self, timeout: Duration) -> Result<(), VaultError> {
+        let probe = Client {
+            breaker: None,
+            success_status_codes: Arc::new(Vec::new()),
+            ..self.clone()
+        };
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut delay = UNSEAL_POLL_MIN;
+        let timed_out = || VaultError::Timeout {
+            operation: "waiting for vault to unseal".to_string(),
+        };
+        loop {
+            // standby nodes are unsealed, so they are reported as healthy too.
+            // Each probe is bounded by the deadline, in case the server never responds.
+            let health = probe.send(
+                &self.inner,
+                reqwest::Method::GET,
+                "sys/health?standbyok=true&perfstandbyok=true",
+                None,
+            );
+            match with_deadline(Some(deadline), health).await {
+                Some(Ok(_)) => return Ok(()),
+                Some(Err(e)) => debug!(error = %e, "waiting for vault to unseal"),
+                None => return Err(timed_out()),
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(timed_out());
+            }
+            tokio::time::sleep(delay.min(deadline - now)).await;
+            delay = (delay * 2).min(UNSEAL_POLL_MAX);
+        }
+    }
+
+    /// Measures the round-trip time of a HEAD request to the server's health endpoint, which
+    /// reads no secrets and needs no permissions. Standby nodes count as healthy. The request is
+    /// sent like any other, so `request_timeout` and the circuit breaker apply, and an error is
+    /// returned if the server is sealed, uninitialized, or unreachable.
+    pub async fn ping(&self) -> Result<Duration, VaultError> {
+        let start = Instant::now();
+        self.send(
+            &self.inner,
+            reqwest::Method::HEAD,
+            "sys/health?standbyok=true&perfstandbyok=true",
+            None,
+        )
+        .await?;
+        Ok(start.elapsed())
+    }
+
+    /// Returns the capabilities of the client's token, such as `["read", "list"]`, on the secret path.
+    /// Capabilities are checked against the kv v2 api path of the secret (`<mount>/data/<path>`).
+    pub async fn capabilities(&self, path: &str) -> Result<Vec<String>, VaultError> {
+        #[derive(serde::Deserialize)]
+        struct CapabilitiesResponse {
+            capabilities: Vec<String>,
+        }
+
+        // policy paths are matched against the unencoded path
+        let (mount, path) = self.resolve(path)?;
+        let api_path = format!("{}/data/{}", mount, path::normalize(&path)?);
+        let res: CapabilitiesResponse = self
+            .request(
+                reqwest::Method::POST,
+                "sys/capabilities-self",
+                Some(&serde_json::json!({ "paths": [api_path] })),
+            )
+            .await?;
+        Ok(res.capabilities)
+    }
+
+    /// Returns the names of the policies attached to the client's token, from `auth/token/lookup-self`.
+    /// The result is cached by the client and its clones for [POLICIES_CACHE_TTL],
+    /// so policy changes may take that long to be seen.
+    pub async fn policies(&self) -> Result<Vec<String>, VaultError> {
+        Ok(self.lookup_self().await?.1.policies)
+    }
+
+    /// Returns the remaining lifetime of the client's token, from `auth/token/lookup-self`,
+    /// or None if the token doesn't expire, such as a root token. Callers can use it to avoid
+    /// starting work that would outlast the token. The lookup is cached for [POLICIES_CACHE_TTL],
+    /// and the time since the lookup is subtracted from its ttl.
+    pub async fn token_ttl(&self) -> Result<Option<Duration>, VaultError> {
+        let (fetched, lookup) = self.lookup_self().await?;
+        Ok(match lookup.ttl {
+            0 => None,
+            ttl => Some(Duration::from_secs(ttl).saturating_sub(fetched.elapsed())),
+        })
+    }
+
+    /// Looks up the client's token, returning the cached lookup if it is recent
+    async fn lookup_self(&self) -> Result<(Instant, TokenLookup), VaultError> {
+        if let Some((fetched, lookup)) = self.token_lookup.lock().unwrap().as_ref() {
+            if fetched.elapsed() < POLICIES_CACHE_TTL {
+                return Ok((*fetched, lookup.clone()));
+            }
+        }
+        let res = self
+            .request::<ApiResponse<TokenLookup>>(
+                reqwest::Method::GET,
+                "auth/token/lookup-self",
+                None,
+            )
+            .await?;
+        let fetched = Instant::now();
+        *self.token_lookup.lock().unwrap() = Some((fetched, res.data.clone()));
+        Ok((fetched, res.data))
+    }
+
+    /// Issues an OIDC identity token for the client's entity with the named role,
+    /// using `identity/oidc/token/:role`. The role must exist and allow the token's entity.
+    /// Workloads can present the returned JWT to services that trust vault's OIDC issuer.
+    pub async fn issue_identity_token(&self, role: &str) -> Result<IdentityToken, VaultError> {
+        let api_path = format!("identity/oidc/token/{}", path::encode_segment(role));
+        let res: ApiResponse<IdentityToken> =
+            self.request(reqwest::Method::GET, &api_path, None).await?;
+        Ok(res.data)
+    }
+
+    /// Returns the enabled auth methods, from `sys/auth`, sorted by path.
+    /// Returns `VaultError::PermissionDenied` if the token may not read `sys/auth`.
+    pub async fn list_auth_methods(&self) -> Result<Vec<AuthMount>, VaultError> {
+        let res: ApiResponse<HashMap<String, AuthMount>> = self
+            .request(reqwest::Method::GET, "sys/auth", None)
+            .await
+            .map_err(|e| self.permission_denied("sys/auth", e))?;
+        let mut mounts = res
+            .data
+            .into_iter()
+            .map(|(path, mount)| AuthMount { path, ..mount })
+            .collect::<Vec<_>>();
+        mounts.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(mounts)
+    }
+
+    /// Returns the metadata of a response-wrapping token, using `sys/wrapping/lookup`,
+    /// without consuming the token. Callers can check where the token was created
+    /// before spending it with [unwrap](Client::unwrap).
+    pub async fn wrap_info(&self, token: &str) -> Result<WrapInfo, VaultError> {
+        let res: ApiResponse<WrapInfo> = self
+            .request(
+                reqwest::Method::POST,
+                "sys/wrapping/lookup",
+                Some(&serde_json::json!({ "token": token })),
+            )
+            .await?;
+        Ok(res.data)
+    }
+
+    /// Returns the wrapped response data of a response-wrapping token, using
+    /// `sys/wrapping/unwrap`. The token is single-use, so it is consumed even if the
+    /// data can't be deserialized into `D`.
+    pub async fn unwrap<D: DeserializeOwned>(&self, token: &str) -> Result<D, VaultError> {
+        let res: ApiResponse<D> = self
+            .request(
+                reqwest::Method::POST,
+                "sys/wrapping/unwrap",
+                Some(&serde_json::json!({ "token": token })),
+            )
+            .await?;
+        Ok(res.data)
+    }
+
+    /// Returns the version of the vault server, from `sys/seal-status`.
+    /// The version is fetched on the first call and cached by the client and its clones.
+    pub async fn server_version(&self) -> Result<semver::Version, VaultError> {
+        #[derive(serde::Deserialize)]
+        struct SealStatusResponse {
+            version: String,
+        }
+
+        if let Some(version) = self.server_version.lock().unwrap().as_ref() {
+            return Ok(version.clone());
+        }
+        let res: SealStatusResponse = self
+            .request(reqwest::Method::GET, "sys/seal-status", None)
+            .await?;
+        let version =
+            semver::Version::parse(&res.version).map_err(|_| VaultError::InvalidServerVersion {
+                version: res.version,
+            })?;
+        *self.server_version.lock().unwrap() = Some(version.clone());
+        Ok(version)
+    }
+
+    /// Returns the version of the kv engine at the client's mount, from `sys/internal/ui/mounts`,
+    /// which unlike `sys/mounts` can be read with any token that has access to the mount.
+    /// If the token is not permitted to read the mount's configuration, a warning is logged and
+    /// V2 is assumed. The version is fetched on the first call and cached by the client and its clones.
+    ///
+    /// Reads, writes, deletes and lists of a v1 mount use the kv v1 api, and operations that
+    /// need versions or metadata return `VaultError::UnsupportedKvVersion`.
+    pub async fn detect_kv_version(&self) -> Result<KvVersion, VaultError> {
+        #[derive(serde::Deserialize)]
+        struct MountResponse {
+            #[serde(default)]
+            options: Option<HashMap<String, String>>,
+        }
+
+        if let Some(version) = self.kv_version.lock().unwrap().get(&self.namespace) {
+            return Ok(*version);
+        }
+        let api_path = format!("sys/internal/ui/mounts/{}", path::encode(&self.namespace));
+        let version = match self
+            .request::<ApiResponse<MountResponse>>(reqwest::Method::GET, &api_path, None)
+            .await
+            .map_err(|e| self.permission_denied("", e))
+        {
+            Ok(res) => match res.data.options.as_ref().and_then(|o| o.get("version")) {
+                Some(version) if version == "2" => KvVersion::V2,
+                _ => KvVersion::V1,
+            },
+            Err(VaultError::PermissionDenied { .. }) => {
+                warn!(mount = %self.namespace, "not permitted to read mount configuration, assuming kv v2");
+                KvVersion::V2
+            }
+            Err(e) => return Err(e),
+        };
+        self.kv_version
+            .lock()
+            .unwrap()
+            .insert(self.namespace.clone(), version);
+        Ok(version)
+    }
+
+    /// Detects the kv engine version of each mount of the client's router, and of the client's
+    /// own mount if the router falls back to it, as [detect_kv_version](Client::detect_kv_version)
+    /// does for one mount. Mounts are detected concurrently, and a failure to detect one mount
+    /// is returned in its entry without affecting the others. Detected versions are cached,
+    /// so this can be called at startup to find v1 mounts before they are used.
+    pub async fn detect_route_versions(&self) -> HashMap<String, Result<KvVersion, VaultError>> {
+        let mut mounts = match &self.router {
+            Some(router) => router.mounts(),
+            None => Vec::new(),
+        };
+        if self.router.as_ref().map(|r| r.fallback).unwrap_or(true) {
+            mounts.push(&self.namespace);
+        }
+        futures::future::join_all(mounts.into_iter().map(|mount| async move {
+            let mut client = self.clone();
+            client.namespace = mount.to_string();
+            (mount.to_string(), client.detect_kv_version().await)
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Returns the version of the kv engine at the mount: the `kv_version` setting for the
+    /// client's configured mount if it is set, and otherwise the version detected on first use
+    /// and cached. A failure to detect the version is logged, and V2 is assumed without caching it,
+    /// so that one mount's failure doesn't affect requests to others.
+    async fn mount_kv_version(&self, mount: &str) -> KvVersion {
+        if let Some(version) = self.known_kv_version(mount) {
+            return version;
+        }
+        let mut client = self.clone();
+        client.namespace = mount.to_string();
+        client.router = None;
+        match client.detect_kv_version().await {
+            Ok(version) => version,
+            Err(e) => {
+                warn!(%mount, error = %e, "failed to detect kv version of mount, assuming kv v2");
+                KvVersion::V2
+            }
+        }
+    }
+
+    /// Returns the version of the kv engine at the mount if it is set or has been detected
+    fn known_kv_version(&self, mount: &str) -> Option<KvVersion> {
+        match &self.configured_kv_version {
+            Some((configured, version)) if configured == mount => Some(*version),
+            _ => self.kv_version.lock().unwrap().get(mount).copied(),
+        }
+    }
+
+    /// Returns `VaultError::UnsupportedKvVersion` if the mount is a kv v1 mount,
+    /// for operations that need the kv v2 api
+    async fn require_kv2(&self, mount: &str) -> Result<(), VaultError> {
+        match self.mount_kv_version(mount).await {
+            KvVersion::V1 => Err(VaultError::UnsupportedKvVersion {
+                mount: mount.to_string(),
+            }),
+            KvVersion::V2 => Ok(()),
+        }
+    }
+
+    /// Returns the kv engine versions detected so far, by mount, for diagnostics
+    pub fn kv_versions(&self) -> HashMap<String, KvVersion> {
+        self.kv_version.lock().unwrap().clone()
+    }
+
+    /// Checks that the server is at least the minimum version required for a feature.
+    /// Returns `VaultError::UnsupportedByServer` if it is older.
+    /// Pre-release and build suffixes of the server version, such as `+ent`, are ignored.
+    pub async fn require_server_version(
+        &self,
+        feature: &str,
+        min_version: &semver::Version,
+    ) -> Result<(), VaultError> {
+        let actual = self.server_version().await?;
+        let release = semver::Version::new(actual.major, actual.minor, actual.patch);
+        if release < *min_version {
+            return Err(VaultError::UnsupportedByServer {
+                feature: feature.to_string(),
+                min_version: min_version.clone(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the number of requests to vault in flight from the client and its clones,
+    /// not counting requests waiting for a slot under `max_concurrent_requests`
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Returns true if the token should be revoked when the client is no longer needed
+    pub fn revoke_on_shutdown(&self) -> bool {
+        self.revoke_on_shutdown
+    }
+
+    /// Revokes the client's token with `auth/token/revoke-self`.
+    /// After this, every request made with the client, or with any other holder of the token, fails.
+    pub async fn revoke_self(&self) -> Result<(), VaultError> {
+        self.send(
+            &self.inner,
+            reqwest::Method::POST,
+            "auth/token/revoke-self",
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Converts a kv v2 read response into an entry of the caller's type
+    async fn entry<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        res: EntryResponse,
+    ) -> Result<SecretEntry<D>, VaultError> {
+        Ok(SecretEntry {
+            data: self.decode(path, res.data).await?,
+            version: res.metadata.version,
+            created_time: res.metadata.created_time,
+            custom_metadata: res.metadata.custom_metadata,
+        })
+    }
+
+    /// Converts a secret value read from vault into the caller's type,
+    /// after decompressing it, removing the data envelope, passing it through the read interceptors, and decoding base64 fields
+    async fn decode<D: DeserializeOwned>(&self, path: &str, value: Value) -> Result<D, VaultError> {
+        let mut value = if self.auto_decompress {
+            compression::decompress(value).map_err(|reason| VaultError::InvalidCompressedData {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+                reason,
+            })?
+        } else {
+            value
+        };
+        if self.unwrap_data_envelope {
+            value = match value {
+                Value::Object(mut map)
+                    if map.len() == 1 && map.contains_key(DATA_ENVELOPE_FIELD) =>
+                {
+                    map.remove(DATA_ENVELOPE_FIELD).unwrap_or_default()
+                }
+                _ => {
+                    return Err(VaultError::MissingEnvelope {
+                        namespace: self.namespace.clone(),
+                        path: path.to_string(),
+                    })
+                }
+            };
+        }
+        for interceptor in self.read_interceptors.iter() {
+            value = interceptor.intercept(path, value).await?;
+        }
+        if self.auto_decode_b64_fields {
+            if let Some(map) = value.as_object_mut() {
+                b64::decode_fields(map).map_err(|field| VaultError::InvalidBase64 {
+                    namespace: self.namespace.clone(),
+                    path: path.to_string(),
+                    field,
+                })?;
+            }
+        }
+        serde_json::from_value(value)
+            .map_err(|source| vaultrs::error::ClientError::JsonParseError { source }.into())
+    }
+
+    /// Converts the caller's data into the secret value written to vault
+    fn encode<T: Serialize>(&self, path: &str, data: &T) -> Result<Value, VaultError> {
+        let mut value = serde_json::to_value(data)
+            .map_err(|source| vaultrs::error::ClientError::JsonParseError { source })?;
+        if self.auto_decode_b64_fields {
+            if let Some(map) = value.as_object_mut() {
+                b64::encode_fields(map);
+            }
+        }
+        if self.unwrap_data_envelope {
+            value = serde_json::json!({ DATA_ENVELOPE_FIELD: value });
+        }
+        require_map(&value)?;
+        let size = serde_json::to_vec(&value).map(|v| v.len()).unwrap_or(0);
+        let value = match self.compress_threshold {
+            Some(threshold) if self.auto_decompress && size >= threshold => {
+                compression::compress(&value, self.compression_codec).map_err(|reason| {
+                    VaultError::InvalidCompressedData {
+                        namespace: self.namespace.clone(),
+                        path: path.to_string(),
+                        reason,
+                    }
+                })?
+            }
+            _ => value,
+        };
+        let size = serde_json::to_vec(&value).map(|v| v.len()).unwrap_or(0);
+        if size > MAX_WRITE_BYTES {
+            return Err(VaultError::InvalidData {
+                reason: format!(
+                    "secret is {} bytes, more than the limit of {} bytes",
+                    size, MAX_WRITE_BYTES
+                ),
+            });
+        }
+        Ok(value)
+    }
+
+    /// Returns the client for the mount that the path is routed to, and the path within that mount.
+    /// Without a router, this is the client itself and the unchanged path.
+    fn route(&self, path: &str) -> Result<(Cow<'_, Client>, String), VaultError> {
+        if self.router.is_none() {
+            return Ok((Cow::Borrowed(self), path.to_string()));
+        }
+        let (mount, rest) = self.resolve(path)?;
+        let mut client = self.clone();
+        client.namespace = mount.to_string();
+        client.router = None;
+        Ok((Cow::Owned(client), rest))
+    }
+
+    /// Returns the mount that the path is routed to, and the path within that mount.
+    /// Without a router, or for the empty path at the root of a listing,
+    /// this is the client's mount and the unchanged path.
+    fn resolve(&self, path: &str) -> Result<(&str, String), VaultError> {
+        let router = match &self.router {
+            Some(router) if !path::normalize_dir(path)?.is_empty() => router,
+            _ => return Ok((self.namespace.as_str(), path.to_string())),
+        };
+        match router.route(path)? {
+            Some((mount, rest)) => Ok((mount, rest)),
+            None if router.fallback => Ok((self.namespace.as_str(), path.to_string())),
+            None => Err(VaultError::InvalidPath {
+                path: path.to_string(),
+                reason: "no mount route matches the path".to_string(),
+            }),
+        }
+    }
+
+    /// Splits an api path into the mount it is in, the client's mount or one of the router's,
+    /// and the rest of the path after the mount
+    fn split_mount<'a>(&'a self, api_path: &'a str) -> Option<(&'a str, &'a str)> {
+        let routed = self.router.iter().flat_map(|router| router.mounts());
+        std::iter::once(self.namespace.as_str())
+            .chain(routed)
+            .filter_map(|mount| {
+                let rest = api_path.strip_prefix(mount)?.strip_prefix('/')?;
+                Some((mount, rest))
+            })
+            .max_by_key(|(mount, _)| mount.len())
+    }
+
+    /// Reads the secret from the primary server, or from the replica if the primary is unavailable
+    async fn read_with_fallback(&self, path: &str) -> Result<ReadSecretResponse, VaultError> {
+        match (self.kv_read(&self.inner, path).await, &self.replica) {
+            (Err(e), Some(replica)) if is_unavailable(&e) => {
+                warn!(error = %e, endpoint = %replica.settings.address, "primary vault read failed, reading from replica");
+                self.kv_read(replica, path).await
+            }
+            (res, _) => res,
+        }
+    }
+
+    /// Reads the metadata of a kv v2 secret, including the state of each version
+    async fn kv_metadata(&self, path: &str) -> Result<ReadSecretMetadataResponse, VaultError> {
+        let api_path = self.metadata_path(path).await?;
+        match self
+            .request::<ApiResponse<ReadSecretMetadataResponse>>(
+                reqwest::Method::GET,
+                &api_path,
+                None,
+            )
+            .await
+        {
+            Ok(res) => Ok(res.data),
+            Err(e) => Err(self.not_found(path, e)),
+        }
+    }
+
+    /// Deletes the metadata and every version of a kv v2 secret
+    async fn kv_delete_metadata(&self, path: &str) -> Result<(), VaultError> {
+        let api_path = self.metadata_path(path).await?;
+        self.send(&self.inner, reqwest::Method::DELETE, &api_path, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Reads the latest version of a kv v2 secret from the server. A secret of a kv v1 mount
+    /// is read with the v1 api and has empty metadata, with version 0.
+    async fn kv_read(
+        &self,
+        target: &VaultClient,
+        path: &str,
+    ) -> Result<ReadSecretResponse, VaultError> {
+        if let Some((_, api_path)) = self.kv1_path(path).await? {
+            let res = self
+                .send(target, reqwest::Method::GET, &api_path, None)
+                .await
+                .map_err(|e| self.not_found(path, e))?;
+            return Ok(ReadSecretResponse {
+                data: res.json::<ApiResponse<Value>>()?.data,
+                metadata: SecretVersionMetadata {
+                    created_time: String::new(),
+                    deletion_time: String::new(),
+                    destroyed: false,
+                    version: 0,
+                },
+            });
+        }
+        let api_path = self.data_path(path).await?;
+        let res = self
+            .send(target, reqwest::Method::GET, &api_path, None)
+            .await
+            .map_err(|e| self.not_found(path, e))?;
+        Ok(res.json::<ApiResponse<ReadSecretResponse>>()?.data)
+    }
+
+    /// Writes a new version of a kv v2 secret, optionally with a check-and-set version.
+    /// If `stamp_provenance` is set, the server version is checked first, and the write's
+    /// provenance is recorded in the key's custom metadata after it.
+    ///
+    /// A secret of a kv v1 mount is written with the v1 api, and the returned metadata is empty.
+    /// A kv v1 mount has no versions to check and no metadata to record provenance in, so
+    /// writes with a check-and-set version, or with `stamp_provenance`, return
+    /// `VaultError::UnsupportedKvVersion`.
+    async fn kv_write(
+        &self,
+        path: &str,
+        data: &Value,
+        cas: Option<u64>,
+    ) -> Result<WriteMetadata, VaultError> {
+        if let Some((mount, api_path)) = self.kv1_path(path).await? {
+            if cas.is_some() || self.stamp_provenance {
+                return Err(VaultError::UnsupportedKvVersion {
+                    mount: mount.to_string(),
+                });
+            }
+            self.send(&self.inner, reqwest::Method::POST, &api_path, Some(data))
+                .await?;
+            return Ok(WriteMetadata::default());
+        }
+        // every write records its provenance here, whichever api the caller used
+        self.require_provenance().await?;
+        let api_path = self.data_path(path).await?;
+        let body = match cas {
+            Some(cas) => serde_json::json!({ "data": data, "options": { "cas": cas } }),
+            None => serde_json::json!({ "data": data }),
+        };
+        let res = match self
+            .send(&self.inner, reqwest::Method::POST, &api_path, Some(&body))
+            .await
+        {
+            Err(VaultError::Client {
+                source: vaultrs::error::ClientError::APIError { code: 400, errors },
+            }) if errors.iter().any(|e| e.contains(CAS_MISMATCH_MESSAGE)) => {
+                return Err(VaultError::CasMismatch {
+                    namespace: self.namespace.clone(),
+                    path: path.to_string(),
+                })
+            }
+            res => res?,
+        };
+        let written = res.json::<ApiResponse<WriteMetadata>>()?.data;
+        if self.stamp_provenance {
+            self.stamp_provenance(path, &written).await?;
+        }
+        Ok(written)
+    }
+
+    /// Lists the keys at a kv v2 path, or at a kv v1 path with the v1 api
+    async fn kv_list(&self, target: &VaultClient, path: &str) -> Result<Vec<String>, VaultError> {
+        let (mount, dir) = self.resolve(path)?;
+        let dir = path::encode(&path::normalize_dir(&dir)?);
+        let api_path = match self.mount_kv_version(mount).await {
+            KvVersion::V1 => format!("{}/{}", mount, dir),
+            KvVersion::V2 => format!("{}/metadata/{}", mount, dir),
+        };
+        let res = self
+            .send(target, list_method(), &api_path, None)
+            .await
+            .map_err(|e| self.not_found(path, e))?;
+        Ok(res.json::<ApiResponse<ListSecretsResponse>>()?.data.keys)
+    }
+
+    /// Returns the api path of the secret's data, with the secret path normalized and encoded
+    async fn data_path(&self, path: &str) -> Result<String, VaultError> {
+        self.secret_path("data", path).await
+    }
+
+    /// Returns the api path of the secret's metadata, with the secret path normalized and encoded
+    async fn metadata_path(&self, path: &str) -> Result<String, VaultError> {
+        self.secret_path("metadata", path).await
+    }
+
+    /// Returns the mount the path is routed to and the api path of the secret, if the mount
+    /// is a kv v1 mount, whose secrets have no data or metadata endpoints
+    async fn kv1_path(&self, path: &str) -> Result<Option<(&str, String)>, VaultError> {
+        let (mount, path) = self.resolve(path)?;
+        match self.mount_kv_version(mount).await {
+            KvVersion::V1 => Ok(Some((
+                mount,
+                format!("{}/{}", mount, path::encode(&path::normalize(&path)?)),
+            ))),
+            KvVersion::V2 => Ok(None),
+        }
+    }
+
+    /// Returns the api path of the kv v2 endpoint for the secret, in the mount the path is routed to.
+    /// Returns `VaultError::UnsupportedKvVersion` if the mount is a kv v1 mount.
+    async fn secret_path(&self, endpoint: &str, path: &str) -> Result<String, VaultError> {
+        let (mount, path) = self.resolve(path)?;
+        self.require_kv2(mount).await?;
+        Ok(format!(
+            "{}/{}/{}",
+            mount,
+            endpoint,
+            path::encode(&path::normalize(&path)?)
+        ))
+    }
+
+    /// Converts a 404 response into `VaultError::NotFound` for the path
+    fn not_found(&self, path: &str, e: VaultError) -> VaultError {
+        match e {
+            VaultError::Client {
+                source: vaultrs::error::ClientError::APIError { code: 404, .. },
+            } => VaultError::NotFound {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+            },
+            e => e,
+        }
+    }
+
+    fn permission_denied(&self, path: &str, e: VaultError) -> VaultError {
+        match e {
+            VaultError::Client {
+                source: vaultrs::error::ClientError::APIError { code: 403, .. },
+            } => VaultError::PermissionDenied {
+                namespace: self.namespace.clone(),
+                path: path.to_string(),
+                missing: Vec::new(),
+            },
+            e => e,
+        }
+    }
+
+    /// Sends a request to the vault http api and deserializes the json response.
+    pub(crate) async fn request<R: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        api_path: &str,
+        body: Option<&Value>,
+    ) -> Result<R, VaultError> {
+        self.send(&self.inner, method, api_path, body).await?.json()
+    }
+
+    /// Sends a request to the vault http api of the target server and returns the successful
+    /// response. Error responses are returned as `vaultrs::error::ClientError::APIError`
+    /// so they are handled like responses from vaultrs.
+    ///
+    /// If consistency is required, the `X-Vault-Index` of the last write is sent with each request,
+    /// and the index returned by each write is saved.
+    ///
+    /// If a circuit breaker is configured and open, requests to the primary server fail
+    /// with `VaultError::CircuitOpen` without being sent.
+    /// If a request signer is configured, the headers it computes are added to each request.
+    /// If trace context propagation is enabled, each request is sent in a child span of the
+    /// current span, and the W3C `traceparent` of that span is sent to vault.
+    ///
+    /// Response bodies larger than the configured maximum are rejected with
+    /// `VaultError::ResponseTooLarge` without reading more than the limit into memory.
+    async fn send(
+        &self,
+        target: &VaultClient,
+        method: reqwest::Method,
+        api_path: &str,
+        body: Option<&Value>,
+    ) -> Result<RawResponse, VaultError> {
+        if self.read_only && is_mutation(&method, api_path, &self.transform_mount) {
+            let res = Err(VaultError::ReadOnly {
+                operation: format!("{} {}", method, api_path),
+            });
+            if let Some(sink) = &self.audit_sink {
+                self.audit(sink.as_ref(), &method, api_path, &res).await;
+            }
+            return res;
+        }
+        let _permit = match &self.limiter {
+            Some(limiter) => {
+                let acquire = limiter.clone().acquire_owned();
+                let deadline = self
+                    .request_queue_timeout
+                    .map(|timeout| tokio::time::Instant::now() + timeout);
+                match with_deadline(deadline, acquire).await {
+                    Some(permit) => Some(permit.expect("request limiter is never closed")),
+                    None => {
+                        let res = Err(VaultError::Timeout {
+                            operation: format!(
+                                "{} {} while queued for a request slot",
+                                method, api_path
+                            ),
+                        });
+                        if let Some(sink) = &self.audit_sink {
+                            self.audit(sink.as_ref(), &method, api_path, &res).await;
+                        }
+                        return res;
+                    }
+                }
+            }
+            None => None,
+        };
+        let _in_flight = InFlight::start(&self.in_flight);
+        let audit = self.audit_sink.as_ref().map(|sink| (sink, method.clone()));
+        let res = match self
+            .send_request(target, method.clone(), api_path, body)
+            .await
+        {
+            Err(VaultError::Client {
+                source: vaultrs::error::ClientError::APIError { code: 403, .. },
+            }) if self.explain_permission_denied => {
+                Err(self.explain_denied(&method, api_path).await)
+            }
+            res => res,
+        };
+        if let Some((sink, method)) = audit {
+            self.audit(sink.as_ref(), &method, api_path, &res).await;
+        }
+        res
+    }
+
+    /// Returns `VaultError::PermissionDenied` for a denied request, with the capabilities
+    /// the token lacks for it, from `sys/capabilities-self`. If the capabilities can't be
+    /// looked up, the error is returned without them.
+    async fn explain_denied(&self, method: &reqwest::Method, api_path: &str) -> VaultError {
+        #[derive(serde::Deserialize)]
+        struct CapabilitiesResponse {
+            capabilities: Vec<String>,
+        }
+
+        // policy paths are matched against the unencoded path, without query parameters
+        let policy_path = path::decode(api_path.split('?').next().unwrap_or_default());
+        let (namespace, path) = match self.split_mount(&policy_path) {
+            Some((mount, rest)) => (
+                mount.to_string(),
+                ["data/", "metadata/"]
+                    .iter()
+                    .find_map(|prefix| rest.strip_prefix(prefix))
+                    .unwrap_or(&policy_path)
+                    .to_string(),
+            ),
+            None => (self.namespace.clone(), policy_path.clone()),
+        };
+        let required: &[&str] = match method.as_str() {
+            "GET" | "HEAD" => &["read"],
+            "LIST" => &["list"],
+            "POST" | "PUT" => &["create", "update"],
+            "PATCH" => &["patch"],
+            "DELETE" => &["delete"],
+            _ => &[],
+        };
+        let body = serde_json::json!({ "paths": [policy_path] });
+        let missing = match self
+            .send_request(
+                &self.inner,
+                reqwest::Method::POST,
+                "sys/capabilities-self",
+                Some(&body),
+            )
+            .await
+            .and_then(|res| res.json::<CapabilitiesResponse>())
+        {
+            Ok(res) if res.capabilities.iter().any(|c| c == "deny") => {
+                required.iter().map(|c| c.to_string()).collect()
+            }
+            Ok(res) => required
+                .iter()
+                .filter(|c| !res.capabilities.iter().any(|held| held == *c))
+                .map(|c| c.to_string())
+                .collect(),
+            Err(e) => {
+                debug!(error = %e, path = %policy_path, "failed to look up capabilities");
+                Vec::new()
+            }
+        };
+        VaultError::PermissionDenied {
+            namespace,
+            path,
+            missing,
+        }
+    }
+
+    /// Records the request in the audit sink, if it accessed a secret
+    async fn audit(
+        &self,
+        sink: &dyn AuditSink,
+        method: &reqwest::Method,
+        api_path: &str,
+        res: &Result<RawResponse, VaultError>,
+    ) {
+        let api_path = api_path.split('?').next().unwrap_or_default();
+        let (mount, rest) = match self.split_mount(api_path) {
+            Some(split) => split,
+            None => return,
+        };
+        // the version of a mount is known by the time a request is sent to it
+        let (metadata, path, delete) = match rest.split_once('/') {
+            _ if self.known_kv_version(mount) == Some(KvVersion::V1) => (false, rest, false),
+            Some(("data", path)) => (false, path, false),
+            Some(("metadata", path)) => (true, path, false),
+            Some(("delete" | "undelete" | "destroy", path)) => (false, path, true),
+            _ => return,
+        };
+        let operation = match method.as_str() {
+            _ if delete => AuditOperation::Delete,
+            "GET" | "HEAD" => AuditOperation::Read,
+            "LIST" => AuditOperation::List,
+            "DELETE" => AuditOperation::Delete,
+            _ => AuditOperation::Write,
+        };
+        let event = AuditEvent {
+            operation,
+            mount: mount.to_string(),
+            path: path::decode(path),
+            metadata,
+            timestamp: std::time::SystemTime::now(),
+            client_id: self.client_id.clone(),
+            correlation_id: self.correlation_id.clone(),
+            outcome: match res {
+                Ok(_) => AuditOutcome::Success,
+                Err(e @ (VaultError::ReadOnly { .. } | VaultError::CircuitOpen { .. })) => {
+                    AuditOutcome::Refused(e.to_string())
+                }
+                Err(e) => AuditOutcome::Failure(e.to_string()),
+            },
+        };
+        if let Err(e) = sink.record(event).await {
+            warn!(error = %e, "failed to record audit event");
+        }
+    }
+
+    /// Sends a request to the vault http api
+    async fn send_request(
+        &self,
+        target: &VaultClient,
+        method: reqwest::Method,
+        api_path: &str,
+        body: Option<&Value>,
+    ) -> Result<RawResponse, VaultError> {
+        let settings = &target.settings;
+        let is_write = !matches!(method.as_str(), "GET" | "HEAD" | "LIST");
+        let is_primary = std::ptr::eq(target, self.inner.as_ref());
+        // writes go straight to the active node once a standby has redirected one to it
+        let active = match is_write && is_primary {
+            true => self.active_addr.lock().unwrap().clone(),
+            false => None,
+        };
+        let url = format!(
+            "{}/v{}/{}",
+            active
+                .as_deref()
+                .unwrap_or_else(|| settings.address.as_str())
+                .trim_end_matches('/'),
+            API_VERSION,
+            api_path
+        );
+        let mut req = target.http.http.request(method.clone(), url);
+        if !settings.token.is_empty() {
+            req = req.header("X-Vault-Token", &settings.token);
+        }
+        if let Some(namespace) = &self.vault_namespace {
+            req = req.header("X-Vault-Namespace", namespace);
+        }
+        if let Some(id) = &self.correlation_id {
+            req = req.header(CORRELATION_ID_HEADER, id);
+        }
+        let span = tracing::debug_span!(
+            "vault_request",
+            method = %method,
+            path = %api_path,
+            correlation_id = ?self.correlation_id
+        );
+        if self.propagate_trace_context {
+            // the caller's span, since the debug-level request span is disabled under most filters,
+            // and a disabled span has no trace context
+            let mut injector = HeaderInjector(Vec::new());
+            TraceContextPropagator::new()
+                .inject_context(&tracing::Span::current().context(), &mut injector);
+            for (name, value) in injector.0 {
+                req = req.header(name, value);
+            }
+        }
+        if let Some(signer) = &self.request_signer {
+            let path = format!("/v{}/{}", API_VERSION, api_path);
+            for (name, value) in signer.sign(method.as_str(), &path) {
+                req = req.header(name, value);
+            }
+        }
+        if self.require_consistency {
+            if let Some(index) = self.last_index.lock().unwrap().as_ref() {
+                req = req
+                    .header(VAULT_INDEX_HEADER, index)
+                    .header("X-Vault-Inconsistent", "forward-active-node");
+            }
+        }
+        if let Some(body) = body {
+            // vault only accepts json merge patches, and json() would replace the content type
+            let content_type = match method == reqwest::Method::PATCH {
+                true => "application/merge-patch+json",
+                false => "application/json",
+            };
+            req = req
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(body.to_string());
+        }
+        // the breaker only tracks the primary server, so reads can still fall back to the replica
+        let breaker = self.breaker.as_ref().filter(|_| is_primary);
+        if let Some(breaker) = breaker {
+            breaker.allow()?;
+        }
+        // one deadline covers connecting, sending the request, and reading the whole response
+        let deadline = self
+            .request_timeout
+            .map(|timeout| tokio::time::Instant::now() + timeout);
+        let timed_out = |phase: &str| VaultError::Timeout {
+            operation: format!("{} {} while {}", method, api_path, phase),
+        };
+        let res = with_deadline(deadline, req.send().instrument(span)).await;
+        if let Some(breaker) = breaker {
+            breaker.record(match &res {
+                Some(Ok(res)) => {
+                    res.status().is_server_error()
+                        && !self.success_status_codes.contains(&res.status().as_u16())
+                }
+                _ => true,
+            });
+        }
+        if is_write && is_primary {
+            match &res {
+                Some(Ok(res)) => {
+                    let origin = res.url().origin().ascii_serialization();
+                    if origin != settings.address.origin().ascii_serialization() {
+                        debug!(active = %origin, "write redirected to active node");
+                        *self.active_addr.lock().unwrap() = Some(origin);
+                    }
+                }
+                // the active node may have changed, so go back to the configured address
+                _ if active.is_some() => *self.active_addr.lock().unwrap() = None,
+                _ => {}
+            }
+        }
+        let res = res.ok_or_else(|| timed_out("connecting or waiting for the response"))??;
+        let status = res.status();
+        if !status.is_success() && !self.success_status_codes.contains(&status.as_u16()) {
+            let server_time = res
+                .headers()
+                .get(reqwest::header::DATE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| httpdate::parse_http_date(v).ok());
+            let errors = with_deadline(deadline, self.read_body(res))
+                .await
+                .and_then(Result::ok)
+                .and_then(|body| serde_json::from_slice::<EndpointError>(&body).ok())
+                .map(|e| e.errors)
+                .unwrap_or_default();
+            if errors.iter().any(|e| e.contains(NOT_YET_VALID_MESSAGE)) {
+                return Err(VaultError::ClockSkew {
+                    hint: clock_skew_hint(server_time),
+                });
+            }
+            return Err(vaultrs::error::ClientError::APIError {
+                code: status.as_u16(),
+                errors,
+            }
+            .into());
+        }
+        if self.require_consistency && is_write {
+            if let Some(index) = res
+                .headers()
+                .get(VAULT_INDEX_HEADER)
+                .and_then(|v| v.to_str().ok())
+            {
+                *self.last_index.lock().unwrap() = Some(index.to_string());
+            }
+        }
+        let headers = res.headers().clone();
+        let body = with_deadline(deadline, self.read_body(res))
+            .await
+            .ok_or_else(|| timed_out("reading the response body"))??;
+        Ok(RawResponse { headers, body })
+    }
+
+    /// Reads the response body, up to the configured maximum size
+    async fn read_body(&self, mut res: reqwest::Response) -> Result<Vec<u8>, VaultError> {
+        let limit = match self.max_response_bytes {
+            Some(limit) => limit,
+            None => return Ok(res.bytes().await?.to_vec()),
+        };
+        if let Some(len) = res.content_length() {
+            if len > limit as u64 {
+                return Err(VaultError::ResponseTooLarge {
+                    limit,
+                    actual: len as usize,
+                });
+            }
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = res.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() > limit {
+                return Err(VaultError::ResponseTooLarge {
+                    limit,
+                    actual: body.len(),
+                });
+            }
+        }
+        Ok(body)
+    }
+}
+
+/// Creates a vault client for the server at `address` with the token and certificates from the config
+pub(crate) fn vault_client(config: &Config, address: Url) -> Result<VaultClient, VaultError> {
+    let mut client = VaultClient::new(VaultClientSettings {
+        token: config.token.clone(),
+        address,
+        ca_certs: config.certs.clone(),
+        verify: false,
+        version: API_VERSION,
+        wrapping: false,
+        timeout: None,
+    })?;
+    // vaultrs doesn't expose connection pool or tls settings, so replace its http client
+    // with one built with the same certificates and the configured connection settings
+    client.http.http = http_client(config)?;
+    Ok(client)
+}
+
+/// Builds the http client used for connections to vault
+fn http_client(config: &Config) -> Result<reqwest::Client, VaultError> {
+    let mut builder = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .pool_idle_timeout(config.pool_idle_timeout)
+        .tcp_keepalive(config.tcp_keepalive)
+        .redirect(match config.follow_redirects {
+            true => reqwest::redirect::Policy::limited(MAX_REDIRECTS),
+            false => reqwest::redirect::Policy::none(),
+        });
+    if let Some(version) = config.min_tls_version {
+        builder = builder.min_tls_version(match version {
+            TlsVersion::Tls1_2 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls1_3 => reqwest::tls::Version::TLS_1_3,
+        });
+    }
+    for path in &config.certs {
+        let content =
+            std::fs::read(path).map_err(|source| vaultrs::error::ClientError::FileReadError {
+                source,
+                path: path.clone(),
+            })?;
+        let cert = reqwest::Certificate::from_pem(&content).map_err(|source| {
+            vaultrs::error::ClientError::ParseCertificateError {
+                source,
+                path: path.clone(),
+            }
+        })?;
+        builder = builder.add_root_certificate(cert);
+    }
+    Ok(builder.build()?)
+}
+
+/// Returns `VaultError::InvalidData` unless the value is a json map, the only kind of value
+/// that can be stored as a kv secret
+pub(crate) fn require_map(value: &Value) -> Result<(), VaultError> {
+    match value.is_object() {
+        true => Ok(()),
+        false => Err(VaultError::InvalidData {
+            reason: format!(
+                "kv secrets must be json maps, but the value is {}",
+                json_kind(value)
+            ),
+        }),
+    }
+}
+
+/// Returns the kind of a json value, for error messages
+fn json_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "a map",
+    }
+}
+
+/// Describes the difference between the server's clock, from its Date header, and the local clock
+fn clock_skew_hint(server_time: Option<std::time::SystemTime>) -> String {
+    let delta =
+        server_time.map(
+            |server| match server.duration_since(std::time::SystemTime::now()) {
+                Ok(ahead) => format!(
+                    "the server clock is {}s ahead of this host",
+                    ahead.as_secs()
+                ),
+                Err(e) => format!(
+                    "the server clock is {}s behind this host",
+                    e.duration().as_secs()
+                ),
+            },
+        );
+    match delta {
+        Some(delta) => format!("{}; synchronize the clocks, such as with NTP", delta),
+        None => "synchronize the clocks of this host and vault, such as with NTP".to_string(),
+    }
+}
+
+/// Generates a correlation id from the current time and a process-wide counter
+fn new_correlation_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!(
+        "{:x}-{:x}-{:x}",
+        nanos,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+/// Counts a request as in flight until it is dropped
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn start(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::SeqCst);
+        InFlight(count)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Runs the future to completion, or returns None if the deadline passes first
+async fn with_deadline<F: std::future::Future>(
+    deadline: Option<tokio::time::Instant>,
+    fut: F,
+) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, fut).await.ok(),
+        None => Some(fut.await),
+    }
+}
+
+/// Returns true if the request could change data in vault. Requests other than GET and LIST
+/// are mutations, except for the POST endpoints that only look up information, and the
+/// encode and decode endpoints of the transform engine at `transform_mount`.
+fn is_mutation(method: &reqwest::Method, api_path: &str, transform_mount: &str) -> bool {
+    const LOOKUPS: [&str; 2] = ["sys/capabilities-self", "sys/wrapping/lookup"];
+    let transform = api_path
+        .strip_prefix(transform_mount)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .map(|rest| rest.starts_with("encode/") || rest.starts_with("decode/"))
+        .unwrap_or(false);
+    !matches!(method.as_str(), "GET" | "HEAD" | "LIST")
+        && !LOOKUPS.contains(&api_path)
+        && !transform
+}
+
+/// Returns true if the error indicates that the server could not be reached or could not
+/// handle the request, as opposed to a response about the request itself (such as 404 or 403)
+fn is_unavailable(e: &VaultError) -> bool {
+    match e {
+        VaultError::Client {
+            source: vaultrs::error::ClientError::APIError { code, .. },
+        } => *code >= 500,
+        VaultError::Client {
+            source: vaultrs::error::ClientError::RestClientError { .. },
+        } => true,
+        VaultError::Http { .. } => true,
+        VaultError::CircuitOpen { .. } => true,
+        VaultError::Timeout { .. } => true,
+        _ => false,
+    }
+}
+
+/// Returns true if the text matches the glob pattern, where `*` matches any sequence
+/// of characters (including none) and `?` matches exactly one character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // position of the last '*' in the pattern, and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, start)) => {
+                    p = star + 1;
+                    t = start + 1;
+                    backtrack = Some((star, start + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// The non-standard http method used by vault to list keys
+fn list_method() -> reqwest::Method {
+    reqwest::Method::from_bytes(b"LIST").unwrap()
 }
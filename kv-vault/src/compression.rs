@@ -0,0 +1,100 @@
+//! Transparent compression of large secrets
+//!
+//! A compressed secret is stored as a map with the codec name in the `__compressed` field
+//! and the base64-encoded compressed json of the original value in the `__payload` field.
+//! Maps with an unrecognized codec name are returned unchanged.
+use std::{
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::Value;
+
+/// Field containing the name of the compression codec
+pub const COMPRESSION_MARKER: &str = "__compressed";
+
+/// Field containing the base64-encoded compressed data
+pub const COMPRESSION_PAYLOAD: &str = "__payload";
+
+/// Compression codecs for stored secrets
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    fn name(&self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "gzip" => Ok(Codec::Gzip),
+            "zstd" => Ok(Codec::Zstd),
+            _ => Err(format!("unsupported compression codec '{}'", s)),
+        }
+    }
+}
+
+/// Compresses the value with the codec, returning the map stored in its place
+pub fn compress(value: &Value, codec: Codec) -> Result<Value, String> {
+    let json = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+    let compressed = match codec {
+        Codec::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&json).map_err(|e| e.to_string())?;
+            encoder.finish().map_err(|e| e.to_string())?
+        }
+        Codec::Zstd => zstd::encode_all(json.as_slice(), 0).map_err(|e| e.to_string())?,
+    };
+    let mut map = serde_json::Map::new();
+    map.insert(
+        COMPRESSION_MARKER.to_string(),
+        Value::String(codec.name().to_string()),
+    );
+    map.insert(
+        COMPRESSION_PAYLOAD.to_string(),
+        Value::String(BASE64.encode(compressed)),
+    );
+    Ok(Value::Object(map))
+}
+
+/// If the value is a compressed secret with a recognized codec, returns the decompressed value.
+/// Other values are returned unchanged.
+pub fn decompress(value: Value) -> Result<Value, String> {
+    let codec = match value
+        .get(COMPRESSION_MARKER)
+        .and_then(Value::as_str)
+        .map(Codec::from_str)
+    {
+        Some(Ok(codec)) => codec,
+        _ => return Ok(value),
+    };
+    let payload = value
+        .get(COMPRESSION_PAYLOAD)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("missing {} field", COMPRESSION_PAYLOAD))?;
+    let compressed = BASE64.decode(payload).map_err(|e| e.to_string())?;
+    let json = match codec {
+        Codec::Gzip => {
+            let mut json = Vec::new();
+            flate2::read::GzDecoder::new(compressed.as_slice())
+                .read_to_end(&mut json)
+                .map_err(|e| e.to_string())?;
+            json
+        }
+        Codec::Zstd => zstd::decode_all(compressed.as_slice()).map_err(|e| e.to_string())?,
+    };
+    serde_json::from_slice(&json).map_err(|e| e.to_string())
+}
@@ -1,11 +1,42 @@
 //! Configuration for kv-vault capability provider
 //!
-use std::{collections::HashMap, env};
+use std::{collections::HashMap, env, str::FromStr, sync::Arc, time::Duration};
 use url::Url;
 use wasmbus_rpc::error::{RpcError, RpcResult};
 
+use crate::{
+    audit::AuditSink,
+    client::KvVersion,
+    compression::Codec,
+    interceptor::ReadInterceptor,
+    router::MountRouter,
+    signer::{HmacSigner, RequestSigner},
+};
+
 const DEFAULT_VAULT_ADDR: &str = "http://127.0.0.1:8200";
 
+/// Default kv mount, which is the kv v2 mount of a vault dev server
+const DEFAULT_MOUNT: &str = "secret";
+
+/// Default mount of the transform secrets engine
+const DEFAULT_TRANSFORM_MOUNT: &str = "transform";
+
+/// Default time an idle connection is kept open. This is shorter than reqwest's default
+/// so that connections are recycled before load balancers with short idle timeouts drop them.
+const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default interval of tcp keepalive probes
+const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Default time the circuit breaker stays open before probing the server
+const DEFAULT_CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Default interval between samples of secret ages
+const DEFAULT_SECRET_AGE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Default limit on the size of response bodies, 8 MiB
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+
 /// KV-Vault configuration
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -16,12 +47,161 @@ pub struct Config {
     /// Defaults to 'http://127.0.0.1:8200'
     pub addr: Url,
     /// Vault mount point, can be set with in environment with VAULT_MOUNT.
-    /// Efaults to "secret/"
+    /// Leading and trailing '/' are removed. Defaults to "secret", which is also used,
+    /// with a warning, if the setting is empty.
     pub mount: String,
+    /// Version of the kv engine at `mount`, can be set in environment with VAULT_KV_VERSION
+    /// as "1", "2", or "auto". None, set with "auto", detects the version of each mount
+    /// with [Client::detect_kv_version](crate::client::Client::detect_kv_version) on its
+    /// first use. Routed mounts are always detected. Defaults to auto.
+    pub kv_version: Option<KvVersion>,
     /// certificate files - path to CA certificate file(s). Setting this enables TLS
     /// The linkdef value `certs` and the environment variable `VAULT_CERTS`
     /// are parsed as a comma-separated string of file paths to generate this list.
     pub certs: Vec<String>,
+    /// Url of a read-only vault (such as a performance standby) used for reads when the primary
+    /// is unavailable, can be set in environment with VAULT_READ_REPLICA_ADDR.
+    /// Writes always go to the primary. Defaults to None (no replica).
+    pub read_replica_addr: Option<Url>,
+    /// If true, secrets stored with a recognized compression marker are decompressed on read,
+    /// can be set in environment with VAULT_AUTO_DECOMPRESS. Defaults to false.
+    pub auto_decompress: bool,
+    /// When auto_decompress is enabled, secrets whose json is at least this many bytes
+    /// are compressed on write, can be set in environment with VAULT_COMPRESS_THRESHOLD.
+    /// Defaults to None (writes are not compressed).
+    pub compress_threshold: Option<usize>,
+    /// Codec used to compress secrets on write ("gzip" or "zstd"),
+    /// can be set in environment with VAULT_COMPRESSION_CODEC. Defaults to gzip.
+    pub compression_codec: Codec,
+    /// If true, fields whose names end in `_b64` are decoded from base64 when read, so they can be
+    /// deserialized as bytes, and encoded when written, can be set in environment with
+    /// VAULT_AUTO_DECODE_B64_FIELDS. Defaults to false.
+    pub auto_decode_b64_fields: bool,
+    /// If true, each secret is stored wrapped in a map with a `data` field, which is removed
+    /// when the secret is read and added when it is written, can be set in environment with
+    /// VAULT_UNWRAP_DATA_ENVELOPE. Reading a secret without the envelope fails. Defaults to false.
+    pub unwrap_data_envelope: bool,
+    /// If true, reads require the server to have the state of the client's most recent write,
+    /// using vault's `X-Vault-Index` header. This provides read-after-write consistency
+    /// with performance standbys. Can be set in environment with VAULT_REQUIRE_CONSISTENCY.
+    /// Defaults to false.
+    pub require_consistency: bool,
+    /// Maximum size of a response body, in bytes, to guard against excessively large secrets,
+    /// can be set in environment with VAULT_MAX_RESPONSE_BYTES. A value of 0 disables the limit.
+    /// Defaults to 8 MiB.
+    pub max_response_bytes: Option<usize>,
+    /// Revoke the token when the link is deleted or the provider shuts down,
+    /// can be set in environment with VAULT_REVOKE_ON_SHUTDOWN. This should not be enabled
+    /// if the token is shared with other links or processes. Defaults to false.
+    pub revoke_on_shutdown: bool,
+    /// Time, in seconds, that an idle connection to vault is kept open for reuse,
+    /// can be set in environment with VAULT_POOL_IDLE_TIMEOUT. A value of 0 disables the timeout.
+    /// Defaults to 30 seconds.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Interval, in seconds, of tcp keepalive probes on connections to vault,
+    /// can be set in environment with VAULT_TCP_KEEPALIVE. A value of 0 disables keepalive.
+    /// Defaults to 60 seconds.
+    pub tcp_keepalive: Option<Duration>,
+    /// Signer that adds headers to each request, for gateways that authenticate requests to vault.
+    /// The linkdef value `request_signing_key` and the environment variable
+    /// VAULT_REQUEST_SIGNING_KEY set a shared key for an [HmacSigner]. Defaults to none.
+    pub request_signer: Option<Arc<dyn RequestSigner>>,
+    /// Send the W3C trace context of the current span with each request, so that vault requests
+    /// appear in distributed traces, can be set in environment with VAULT_PROPAGATE_TRACE_CONTEXT.
+    /// Defaults to false.
+    pub propagate_trace_context: bool,
+    /// Minimum TLS protocol version of connections to vault, can be set in environment with
+    /// VAULT_MIN_TLS_VERSION as "1.2" or "1.3". Defaults to TLS 1.2.
+    pub min_tls_version: Option<TlsVersion>,
+    /// Interceptors applied, in order, to each secret that is read. These can only be set
+    /// programmatically. Defaults to none.
+    pub read_interceptors: Vec<Arc<dyn ReadInterceptor>>,
+    /// Sink that receives an event for each access to a secret. This can only be set
+    /// programmatically. Defaults to none.
+    pub audit_sink: Option<Arc<dyn AuditSink>>,
+    /// Routes from path prefixes to mounts, used by reads and writes of secrets.
+    /// The linkdef value `mount_routes` and the environment variable VAULT_MOUNT_ROUTES
+    /// are parsed as a comma-separated list of `prefix=mount` routes. Paths that match no route
+    /// use `mount`, unless `mount_routes_fallback` (VAULT_MOUNT_ROUTES_FALLBACK) is false.
+    /// Defaults to none.
+    pub mount_router: Option<MountRouter>,
+    /// Number of consecutive failed requests, because vault was unreachable or returned a 5xx error,
+    /// after which requests are rejected without being sent, can be set in environment with
+    /// VAULT_CIRCUIT_BREAKER_THRESHOLD. Defaults to none, which disables the circuit breaker.
+    pub circuit_breaker_threshold: Option<u32>,
+    /// Time, in seconds, that requests are rejected after the circuit breaker opens,
+    /// before one request is sent to probe the server, can be set in environment with
+    /// VAULT_CIRCUIT_BREAKER_COOLDOWN. Defaults to 30 seconds.
+    pub circuit_breaker_cooldown: Duration,
+    /// Time, in seconds, allowed for each request to vault, from resolving the server's address
+    /// to reading the end of the response, can be set in environment with VAULT_REQUEST_TIMEOUT.
+    /// A value of 0 disables the timeout. Defaults to none.
+    pub request_timeout: Option<Duration>,
+    /// Http status codes, outside 200-299, that are also treated as successful responses,
+    /// for gateways in front of vault that return non-standard codes, can be set in environment
+    /// with VAULT_SUCCESS_STATUS_CODES as a comma-separated list. Defaults to none.
+    pub success_status_codes: Option<Vec<u16>>,
+    /// If true, redirects are followed, such as those from a standby node to the active node,
+    /// and later writes are sent to the active node directly, can be set in environment
+    /// with VAULT_FOLLOW_REDIRECTS. Defaults to true.
+    pub follow_redirects: bool,
+    /// Maximum number of requests to vault in flight at once from a client, can be set in
+    /// environment with VAULT_MAX_CONCURRENT_REQUESTS. Further requests wait for a slot.
+    /// A value of 0 disables the limit. Defaults to none.
+    pub max_concurrent_requests: Option<usize>,
+    /// Time, in seconds, that a request waits for a slot under `max_concurrent_requests` before
+    /// failing, can be set in environment with VAULT_REQUEST_QUEUE_TIMEOUT.
+    /// A value of 0 disables the timeout. Defaults to none.
+    pub request_queue_timeout: Option<Duration>,
+    /// If true, each write of a secret records the provider, the time of the write,
+    /// and `client_id` in the key's custom metadata, can be set in environment with
+    /// VAULT_STAMP_PROVENANCE. Defaults to false.
+    pub stamp_provenance: bool,
+    /// Identifier of this client recorded by `stamp_provenance`, can be set in environment
+    /// with VAULT_CLIENT_ID. Defaults to none, in which case no client_id is recorded.
+    pub client_id: Option<String>,
+    /// If true, the client refuses any request that would change data in vault, such as
+    /// writes and deletes, with `VaultError::ReadOnly`, without sending it. This is a guard in
+    /// the client, not a restriction enforced by vault; the token may still allow writes.
+    /// Reads are not affected. Can be set in environment with VAULT_READ_ONLY. Defaults to false.
+    pub read_only: bool,
+    /// Mount point of the transform secrets engine used by [Client::transform](crate::client::Client::transform),
+    /// can be set in environment with VAULT_TRANSFORM_MOUNT. Defaults to "transform".
+    pub transform_mount: String,
+    /// If true, when vault denies a request, the token's capabilities for the path are looked up
+    /// with `sys/capabilities-self`, and `VaultError::PermissionDenied` lists the capabilities
+    /// the token's policies lack. This adds a request to each denied request. Can be set in
+    /// environment with VAULT_EXPLAIN_PERMISSION_DENIED. Defaults to false.
+    pub explain_permission_denied: bool,
+    /// Paths of secrets whose age, the time since their latest version was created, is sampled
+    /// in the background and reported by [Client::sample_secret_ages](crate::client::Client::sample_secret_ages),
+    /// can be set in environment with VAULT_SECRET_AGE_PATHS as a comma-separated list.
+    /// Defaults to none, which disables the sampler.
+    pub secret_age_paths: Vec<String>,
+    /// Time, in seconds, between samples of `secret_age_paths`, can be set in environment
+    /// with VAULT_SECRET_AGE_INTERVAL. Defaults to 300 seconds.
+    pub secret_age_interval: Duration,
+}
+
+/// TLS protocol versions that may be required of connections to vault.
+/// Versions before 1.2 are not supported by the client's TLS implementation,
+/// so they are never negotiated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TlsVersion {
+    Tls1_2,
+    Tls1_3,
+}
+
+impl FromStr for TlsVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().trim_start_matches("tls") {
+            "1.2" => Ok(TlsVersion::Tls1_2),
+            "1.3" => Ok(TlsVersion::Tls1_3),
+            _ => Err(format!("unsupported tls version '{}'", s)),
+        }
+    }
 }
 
 impl Default for Config {
@@ -32,13 +212,14 @@ impl Default for Config {
 }
 
 impl Config {
-    /// initialize from linkdef values, environment, and defaults
+    /// initialize from linkdef values, environment, and defaults.
+    ///
+    /// Setting values may reference environment variables as `${VAR}`, or `${VAR:-default}`
+    /// to use a default when VAR is not set. `$$` is a literal '$'.
+    /// A reference to an undefined variable without a default is an error.
     pub fn from_values(values: &HashMap<String, String>) -> RpcResult<Config> {
         let config = Config {
-            addr: env::var("VAULT_ADDR")
-                .ok()
-                .or_else(|| values.get("addr").cloned())
-                .or_else(|| values.get("ADDR").cloned())
+            addr: setting(values, "VAULT_ADDR", "addr")?
                 .unwrap_or_else(|| DEFAULT_VAULT_ADDR.to_string())
                 .parse()
                 .unwrap_or_else(|_| {
@@ -48,27 +229,377 @@ impl Config {
                     );
                     DEFAULT_VAULT_ADDR.parse().unwrap()
                 }),
-            token: env::var("VAULT_TOKEN")
-                .ok()
-                .or_else(|| values.get("token").cloned())
-                .or_else(|| values.get("TOKEN").cloned())
-                .ok_or_else(|| {
-                    RpcError::ProviderInit("missing setting for 'token' or VAULT_TOKEN".to_string())
-                })?,
-            mount: env::var("VAULT_MOUNT")
-                .ok()
-                .or_else(|| values.get("mount").cloned())
-                .or_else(|| values.get("MOUNT").cloned())
-                .unwrap_or_else(|| "secret".to_string()),
-            certs: match env::var("VAULT_CERTS")
-                .ok()
-                .or_else(|| values.get("certs").cloned())
-                .or_else(|| values.get("CERTS").cloned())
-            {
+            token: setting(values, "VAULT_TOKEN", "token")?.ok_or_else(|| {
+                RpcError::ProviderInit("missing setting for 'token' or VAULT_TOKEN".to_string())
+            })?,
+            mount: match setting(values, "VAULT_MOUNT", "mount")? {
+                Some(mount) if !mount.trim().trim_matches('/').is_empty() => {
+                    mount.trim().trim_matches('/').to_string()
+                }
+                Some(_) => {
+                    eprintln!("VAULT_MOUNT is empty, using default of {}", DEFAULT_MOUNT);
+                    DEFAULT_MOUNT.to_string()
+                }
+                None => DEFAULT_MOUNT.to_string(),
+            },
+            kv_version: match setting(values, "VAULT_KV_VERSION", "kv_version")? {
+                Some(version) => parse_kv_version(&version)?,
+                None => None,
+            },
+            certs: match setting(values, "VAULT_CERTS", "certs")? {
                 Some(certs) => certs.split(',').map(|s| s.trim().to_string()).collect(),
                 _ => Vec::new(),
             },
+            read_replica_addr: match setting(
+                values,
+                "VAULT_READ_REPLICA_ADDR",
+                "read_replica_addr",
+            )? {
+                Some(addr) => Some(addr.parse().map_err(|_| {
+                    RpcError::ProviderInit(format!("invalid read_replica_addr url '{}'", addr))
+                })?),
+                None => None,
+            },
+            auto_decompress: match setting(values, "VAULT_AUTO_DECOMPRESS", "auto_decompress")? {
+                Some(value) => parse_bool("auto_decompress", &value)?,
+                None => false,
+            },
+            compress_threshold: match setting(
+                values,
+                "VAULT_COMPRESS_THRESHOLD",
+                "compress_threshold",
+            )? {
+                Some(value) => Some(value.parse().map_err(|_| {
+                    RpcError::ProviderInit(format!("invalid compress_threshold '{}'", value))
+                })?),
+                None => None,
+            },
+            compression_codec: match setting(
+                values,
+                "VAULT_COMPRESSION_CODEC",
+                "compression_codec",
+            )? {
+                Some(value) => value.parse().map_err(RpcError::ProviderInit)?,
+                None => Codec::default(),
+            },
+            auto_decode_b64_fields: match setting(
+                values,
+                "VAULT_AUTO_DECODE_B64_FIELDS",
+                "auto_decode_b64_fields",
+            )? {
+                Some(value) => parse_bool("auto_decode_b64_fields", &value)?,
+                None => false,
+            },
+            unwrap_data_envelope: match setting(
+                values,
+                "VAULT_UNWRAP_DATA_ENVELOPE",
+                "unwrap_data_envelope",
+            )? {
+                Some(value) => parse_bool("unwrap_data_envelope", &value)?,
+                None => false,
+            },
+            require_consistency: match setting(
+                values,
+                "VAULT_REQUIRE_CONSISTENCY",
+                "require_consistency",
+            )? {
+                Some(value) => parse_bool("require_consistency", &value)?,
+                None => false,
+            },
+            max_response_bytes: match setting(
+                values,
+                "VAULT_MAX_RESPONSE_BYTES",
+                "max_response_bytes",
+            )? {
+                Some(value) => match value.parse().map_err(|_| {
+                    RpcError::ProviderInit(format!("invalid max_response_bytes '{}'", value))
+                })? {
+                    0 => None,
+                    limit => Some(limit),
+                },
+                None => Some(DEFAULT_MAX_RESPONSE_BYTES),
+            },
+            revoke_on_shutdown: match setting(
+                values,
+                "VAULT_REVOKE_ON_SHUTDOWN",
+                "revoke_on_shutdown",
+            )? {
+                Some(value) => parse_bool("revoke_on_shutdown", &value)?,
+                None => false,
+            },
+            pool_idle_timeout: match setting(
+                values,
+                "VAULT_POOL_IDLE_TIMEOUT",
+                "pool_idle_timeout",
+            )? {
+                Some(value) => parse_secs("pool_idle_timeout", &value)?,
+                None => Some(DEFAULT_POOL_IDLE_TIMEOUT),
+            },
+            tcp_keepalive: match setting(values, "VAULT_TCP_KEEPALIVE", "tcp_keepalive")? {
+                Some(value) => parse_secs("tcp_keepalive", &value)?,
+                None => Some(DEFAULT_TCP_KEEPALIVE),
+            },
+            request_signer: setting(values, "VAULT_REQUEST_SIGNING_KEY", "request_signing_key")?
+                .map(|key| Arc::new(HmacSigner::new(key.as_bytes())) as Arc<dyn RequestSigner>),
+            propagate_trace_context: match setting(
+                values,
+                "VAULT_PROPAGATE_TRACE_CONTEXT",
+                "propagate_trace_context",
+            )? {
+                Some(value) => parse_bool("propagate_trace_context", &value)?,
+                None => false,
+            },
+            min_tls_version: match setting(values, "VAULT_MIN_TLS_VERSION", "min_tls_version")? {
+                Some(value) => Some(value.parse().map_err(RpcError::ProviderInit)?),
+                None => Some(TlsVersion::Tls1_2),
+            },
+            read_interceptors: Vec::new(),
+            audit_sink: None,
+            mount_router: match setting(values, "VAULT_MOUNT_ROUTES", "mount_routes")? {
+                Some(routes) => {
+                    let fallback = match setting(
+                        values,
+                        "VAULT_MOUNT_ROUTES_FALLBACK",
+                        "mount_routes_fallback",
+                    )? {
+                        Some(value) => parse_bool("mount_routes_fallback", &value)?,
+                        None => true,
+                    };
+                    Some(parse_routes(&routes, fallback)?)
+                }
+                None => None,
+            },
+            circuit_breaker_threshold: match setting(
+                values,
+                "VAULT_CIRCUIT_BREAKER_THRESHOLD",
+                "circuit_breaker_threshold",
+            )? {
+                Some(value) => match value.parse().map_err(|_| {
+                    RpcError::ProviderInit(format!("invalid circuit_breaker_threshold '{}'", value))
+                })? {
+                    0 => None,
+                    threshold => Some(threshold),
+                },
+                None => None,
+            },
+            circuit_breaker_cooldown: match setting(
+                values,
+                "VAULT_CIRCUIT_BREAKER_COOLDOWN",
+                "circuit_breaker_cooldown",
+            )? {
+                Some(value) => parse_secs("circuit_breaker_cooldown", &value)?.unwrap_or_default(),
+                None => DEFAULT_CIRCUIT_BREAKER_COOLDOWN,
+            },
+            request_timeout: match setting(values, "VAULT_REQUEST_TIMEOUT", "request_timeout")? {
+                Some(value) => parse_secs("request_timeout", &value)?,
+                None => None,
+            },
+            success_status_codes: match setting(
+                values,
+                "VAULT_SUCCESS_STATUS_CODES",
+                "success_status_codes",
+            )? {
+                Some(codes) => Some(parse_status_codes(&codes)?),
+                None => None,
+            },
+            follow_redirects: match setting(values, "VAULT_FOLLOW_REDIRECTS", "follow_redirects")? {
+                Some(value) => parse_bool("follow_redirects", &value)?,
+                None => true,
+            },
+            max_concurrent_requests: match setting(
+                values,
+                "VAULT_MAX_CONCURRENT_REQUESTS",
+                "max_concurrent_requests",
+            )? {
+                Some(value) => match value.parse().map_err(|_| {
+                    RpcError::ProviderInit(format!("invalid max_concurrent_requests '{}'", value))
+                })? {
+                    0 => None,
+                    max => Some(max),
+                },
+                None => None,
+            },
+            request_queue_timeout: match setting(
+                values,
+                "VAULT_REQUEST_QUEUE_TIMEOUT",
+                "request_queue_timeout",
+            )? {
+                Some(value) => parse_secs("request_queue_timeout", &value)?,
+                None => None,
+            },
+            stamp_provenance: match setting(values, "VAULT_STAMP_PROVENANCE", "stamp_provenance")? {
+                Some(value) => parse_bool("stamp_provenance", &value)?,
+                None => false,
+            },
+            client_id: setting(values, "VAULT_CLIENT_ID", "client_id")?,
+            read_only: match setting(values, "VAULT_READ_ONLY", "read_only")? {
+                Some(value) => parse_bool("read_only", &value)?,
+                None => false,
+            },
+            transform_mount: setting(values, "VAULT_TRANSFORM_MOUNT", "transform_mount")?
+                .map(|mount| mount.trim().trim_matches('/').to_string())
+                .filter(|mount| !mount.is_empty())
+                .unwrap_or_else(|| DEFAULT_TRANSFORM_MOUNT.to_string()),
+            explain_permission_denied: match setting(
+                values,
+                "VAULT_EXPLAIN_PERMISSION_DENIED",
+                "explain_permission_denied",
+            )? {
+                Some(value) => parse_bool("explain_permission_denied", &value)?,
+                None => false,
+            },
+            secret_age_paths: match setting(values, "VAULT_SECRET_AGE_PATHS", "secret_age_paths")? {
+                Some(paths) => paths
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .map(String::from)
+                    .collect(),
+                None => Vec::new(),
+            },
+            secret_age_interval: match setting(
+                values,
+                "VAULT_SECRET_AGE_INTERVAL",
+                "secret_age_interval",
+            )? {
+                Some(value) => parse_secs("secret_age_interval", &value)?.ok_or_else(|| {
+                    RpcError::ProviderInit(
+                        "invalid setting for 'secret_age_interval': must be more than 0"
+                            .to_string(),
+                    )
+                })?,
+                None => DEFAULT_SECRET_AGE_INTERVAL,
+            },
         };
         Ok(config)
     }
 }
+
+/// Returns the setting from the environment, or from the linkdef value in lowercase or uppercase,
+/// with environment variable references expanded
+fn setting(
+    values: &HashMap<String, String>,
+    env_name: &str,
+    key: &str,
+) -> RpcResult<Option<String>> {
+    env::var(env_name)
+        .ok()
+        .or_else(|| values.get(key).cloned())
+        .or_else(|| values.get(&key.to_uppercase()).cloned())
+        .map(|value| {
+            interpolate(&value).map_err(|e| {
+                RpcError::ProviderInit(format!("invalid setting for '{}': {}", key, e))
+            })
+        })
+        .transpose()
+}
+
+/// Parses a boolean setting
+fn parse_bool(key: &str, value: &str) -> RpcResult<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Ok(true),
+        "false" | "0" | "no" => Ok(false),
+        _ => Err(RpcError::ProviderInit(format!(
+            "invalid setting for '{}': expected true or false",
+            key
+        ))),
+    }
+}
+
+/// Parses a kv engine version, returning None for "auto"
+fn parse_kv_version(value: &str) -> RpcResult<Option<KvVersion>> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "auto" => Ok(None),
+        "1" | "v1" => Ok(Some(KvVersion::V1)),
+        "2" | "v2" => Ok(Some(KvVersion::V2)),
+        _ => Err(RpcError::ProviderInit(
+            "invalid setting for 'kv_version': expected 1, 2, or auto".to_string(),
+        )),
+    }
+}
+
+/// Parses a comma-separated list of `prefix=mount` routes
+fn parse_routes(routes: &str, fallback: bool) -> RpcResult<MountRouter> {
+    let mut router = MountRouter::new(fallback);
+    for route in routes.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+        let (prefix, mount) = route.split_once('=').ok_or_else(|| {
+            RpcError::ProviderInit(format!(
+                "invalid setting for 'mount_routes': expected prefix=mount, got '{}'",
+                route
+            ))
+        })?;
+        router = router
+            .route_prefix(prefix.trim(), mount.trim())
+            .map_err(|e| {
+                RpcError::ProviderInit(format!("invalid setting for 'mount_routes': {}", e))
+            })?;
+    }
+    Ok(router)
+}
+
+/// Parses a comma-separated list of http status codes
+fn parse_status_codes(codes: &str) -> RpcResult<Vec<u16>> {
+    codes
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|code| match code.parse::<u16>() {
+            Ok(code) if (100..=599).contains(&code) => Ok(code),
+            _ => Err(RpcError::ProviderInit(format!(
+                "invalid setting for 'success_status_codes': '{}' is not an http status code",
+                code
+            ))),
+        })
+        .collect()
+}
+
+/// Parses a duration setting in whole seconds, where 0 means none
+fn parse_secs(key: &str, value: &str) -> RpcResult<Option<Duration>> {
+    match value.parse::<u64>() {
+        Ok(0) => Ok(None),
+        Ok(secs) => Ok(Some(Duration::from_secs(secs))),
+        Err(_) => Err(RpcError::ProviderInit(format!(
+            "invalid setting for '{}': expected a number of seconds",
+            key
+        ))),
+    }
+}
+
+/// Expands `${VAR}` and `${VAR:-default}` references from the process environment,
+/// and `$$` to a literal '$'
+fn interpolate(value: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            // the value may be a credential, so only its offset is reported
+            let end = after.find('}').ok_or_else(|| {
+                format!(
+                    "unterminated variable reference at offset {}",
+                    value.len() - rest.len() - 1
+                )
+            })?;
+            let (name, default) = match after[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&after[..end], None),
+            };
+            match (env::var(name), default) {
+                (Ok(var), _) => result.push_str(&var),
+                (Err(_), Some(default)) => result.push_str(default),
+                (Err(_), None) => {
+                    return Err(format!("environment variable '{}' is not set", name))
+                }
+            }
+            rest = &after[end + 1..];
+        } else {
+            result.push('$');
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
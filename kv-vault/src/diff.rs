@@ -0,0 +1,68 @@
+//! Field-level comparison of two versions of a secret
+//!
+//! Changes are reported per top-level field. Values are redacted by default: each is
+//! replaced with a short hash of its json, so reviewers can see that a value changed,
+//! and whether two values are equal, without seeing the value.
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// How a field differs between two versions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A field that differs between two versions of a secret
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: String,
+    pub kind: ChangeKind,
+    /// The field's value in the older version, or None if it was added.
+    /// This is a hash of the value unless values were requested.
+    pub from: Option<String>,
+    /// The field's value in the newer version, or None if it was removed.
+    /// This is a hash of the value unless values were requested.
+    pub to: Option<String>,
+}
+
+/// Returns the changes to top-level fields from one value to another, sorted by field name.
+/// If `include_values` is false, values are reported as `sha256:` followed by the first
+/// 16 hex digits of the hash of their json. Values that are not maps have no fields.
+pub fn diff_fields(from: &Value, to: &Value, include_values: bool) -> Vec<FieldChange> {
+    let empty = serde_json::Map::new();
+    let from = from.as_object().unwrap_or(&empty);
+    let to = to.as_object().unwrap_or(&empty);
+    let show = |value: &Value| {
+        let json = value.to_string();
+        if include_values {
+            json
+        } else {
+            let hash = Sha256::digest(json.as_bytes());
+            let hex: String = hash.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+            format!("sha256:{}", hex)
+        }
+    };
+    from.keys()
+        .chain(to.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|field| {
+            let (kind, old, new) = match (from.get(field), to.get(field)) {
+                (None, Some(new)) => (ChangeKind::Added, None, Some(new)),
+                (Some(old), None) => (ChangeKind::Removed, Some(old), None),
+                (Some(old), Some(new)) if old != new => (ChangeKind::Changed, Some(old), Some(new)),
+                _ => return None,
+            };
+            Some(FieldChange {
+                field: field.clone(),
+                kind,
+                from: old.map(show),
+                to: new.map(show),
+            })
+        })
+        .collect()
+}
@@ -1,4 +1,5 @@
 //! internal errors generated by kv-vault
+use crate::format::SecretFormat;
 
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
@@ -9,10 +10,193 @@ pub enum VaultError {
     #[error("Key not found: namespace/key {namespace}/{path}")]
     NotFound { namespace: String, path: String },
 
+    /// A compressed secret could not be decompressed, or a secret could not be compressed
+    #[error("Compressed data error for namespace/key {namespace}/{path}: {reason}")]
+    InvalidCompressedData {
+        namespace: String,
+        path: String,
+        reason: String,
+    },
+
+    /// The chunks of a large secret are missing or don't match its manifest
+    #[error("Chunked data error for namespace/key {namespace}/{path}: {reason}")]
+    InvalidChunkedData {
+        namespace: String,
+        path: String,
+        reason: String,
+    },
+
+    /// The value can't be written as a secret
+    #[error("Invalid secret data: {reason}")]
+    InvalidData { reason: String },
+
+    /// The secret is not wrapped in the `data` envelope that `unwrap_data_envelope` expects
+    #[error("Missing data envelope in namespace/key {namespace}/{path}")]
+    MissingEnvelope { namespace: String, path: String },
+
+    /// The secret path is empty or malformed
+    #[error("Invalid path '{path}': {reason}")]
+    InvalidPath { path: String, reason: String },
+
+    /// Some deletes of a bulk delete failed. `errors` has the key and error of each failure.
+    #[error("Deleted {deleted} secrets, failed to delete {}", .errors.len())]
+    PartialDelete {
+        deleted: usize,
+        errors: Vec<(String, VaultError)>,
+    },
+
+    /// The token does not have permission for the operation. `missing` lists the capabilities
+    /// the token's policies lack for the operation, if they were looked up, which is only done
+    /// when `explain_permission_denied` is set.
+    #[error("Permission denied: namespace/key {namespace}/{path}{}", missing_hint(.missing))]
+    PermissionDenied {
+        namespace: String,
+        path: String,
+        missing: Vec<String>,
+    },
+
+    /// A conditional write was rejected because the secret version has changed
+    #[error("Version mismatch: namespace/key {namespace}/{path} has been modified")]
+    CasMismatch { namespace: String, path: String },
+
+    /// The secret does not contain the requested field
+    #[error("Field '{field}' not found: namespace/key {namespace}/{path}")]
+    FieldNotFound {
+        namespace: String,
+        path: String,
+        field: String,
+    },
+
+    /// The field was expected to contain base64-encoded data
+    #[error("Field '{field}' is not valid base64: namespace/key {namespace}/{path}")]
+    InvalidBase64 {
+        namespace: String,
+        path: String,
+        field: String,
+    },
+
+    /// The secret's text could not be parsed or rendered in the requested format
+    #[error("Invalid {format} secret at namespace/key {namespace}/{path}: {reason}")]
+    InvalidFormat {
+        namespace: String,
+        path: String,
+        format: SecretFormat,
+        reason: String,
+    },
+
+    /// The secret has fields that the type it was read into does not
+    #[error("Secret namespace/key {namespace}/{path} does not match the expected type, extra fields {extra:?}, missing fields {missing:?}")]
+    SchemaMismatch {
+        namespace: String,
+        path: String,
+        extra: Vec<String>,
+        missing: Vec<String>,
+    },
+
+    /// The response from vault exceeded the configured maximum size.
+    /// `actual` is the declared content length, or the number of bytes read before the limit was exceeded.
+    #[error("Response too large: {actual} bytes exceeds limit of {limit} bytes")]
+    ResponseTooLarge { limit: usize, actual: usize },
+
+    /// The requested version of the secret has been destroyed, and its data can't be read
+    #[error("Version {version} of namespace/key {namespace}/{path} has been destroyed")]
+    VersionDestroyed {
+        namespace: String,
+        path: String,
+        version: u64,
+    },
+
+    /// The secret's latest version is older than the version required by the caller
+    #[error("Stale version {available} of namespace/key {namespace}/{path}, required version {required}")]
+    StaleVersion {
+        namespace: String,
+        path: String,
+        available: u64,
+        required: u64,
+    },
+
+    /// A swap of two secrets failed after the first secret was written, and writing its previous
+    /// value back also failed, so it holds the other secret's value at the given version
+    #[error("Swap left namespace/key {namespace}/{path} at version {version} with the other value, rollback failed: {reason}")]
+    SwapIncomplete {
+        namespace: String,
+        path: String,
+        version: u64,
+        reason: String,
+    },
+
+    /// The version read back after a write does not match the data written
+    #[error("Version {version} of namespace/key {namespace}/{path} read back differs from the data written")]
+    VerificationFailed {
+        namespace: String,
+        path: String,
+        version: u64,
+    },
+
+    /// The vault server is older than the version that added a feature
+    #[error("{feature} requires vault {min_version} or later, server is version {actual}")]
+    UnsupportedByServer {
+        feature: String,
+        min_version: semver::Version,
+        actual: semver::Version,
+    },
+
+    /// The feature is only available in vault enterprise, and the server is not an enterprise build
+    #[error("{feature} requires Vault Enterprise, server is version {actual}")]
+    RequiresEnterprise {
+        feature: String,
+        actual: semver::Version,
+    },
+
+    /// The mount is a kv v1 mount, and the operation needs the kv v2 api
+    #[error("Mount {mount} is a kv v1 mount, which does not support this operation")]
+    UnsupportedKvVersion { mount: String },
+
+    /// The vault server reported a version that is not a semantic version
+    #[error("Invalid vault server version '{version}'")]
+    InvalidServerVersion { version: String },
+
+    /// The operation did not complete before its deadline
+    #[error("Timed out {operation}")]
+    Timeout { operation: String },
+
+    /// Requests to vault are being rejected without being sent, because recent requests failed.
+    /// `retry_after` is the time until a request will be allowed through to probe the server.
+    #[error("Vault unavailable, circuit breaker open, retry after {retry_after:?}")]
+    CircuitOpen { retry_after: std::time::Duration },
+
+    /// The client is configured as read-only, and the request would change data in vault
+    #[error("Client is read-only, refused {operation}")]
+    ReadOnly { operation: String },
+
+    /// Vault rejected a token or credential as not yet valid, which is usually caused by
+    /// the clocks of vault and this host, or of the credential's issuer, being out of sync
+    #[error("Token not yet valid, check for clock skew: {hint}")]
+    ClockSkew { hint: String },
+
+    /// The operation was cancelled by the caller before it completed
+    #[error("Cancelled {operation}")]
+    Cancelled { operation: String },
+
     /// All other errors
     #[error("An error occurred with the request")]
     Client {
         #[from]
         source: vaultrs::error::ClientError,
     },
+
+    /// Error sending a request, or reading its response, for endpoints not covered by vaultrs
+    #[error("An error occurred sending the request")]
+    Http {
+        #[from]
+        source: reqwest::Error,
+    },
+}
+
+/// Describes the missing capabilities of a permission denied error, if any are known
+fn missing_hint(missing: &[String]) -> String {
+    match missing {
+        [] => String::new(),
+        missing => format!(", policy needs {}", missing.join(" or ")),
+    }
 }
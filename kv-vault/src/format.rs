@@ -0,0 +1,59 @@
+//! Text formats for secrets stored as a single string field
+//!
+//! Vault stores each secret as a json map. Secrets that are naturally TOML or YAML documents
+//! are stored as a string in the [STRING_VALUE_MARKER](crate::STRING_VALUE_MARKER) field,
+//! the same field used by the provider for plain string values.
+use std::{fmt, str::FromStr};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Formats of a secret stored as text
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecretFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl SecretFormat {
+    /// Parses the text in this format
+    pub fn parse<D: DeserializeOwned>(&self, text: &str) -> Result<D, String> {
+        match self {
+            SecretFormat::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+            SecretFormat::Toml => toml::from_str(text).map_err(|e| e.to_string()),
+            SecretFormat::Yaml => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Serializes the data as text in this format
+    pub fn render<T: Serialize + ?Sized>(&self, data: &T) -> Result<String, String> {
+        match self {
+            SecretFormat::Json => serde_json::to_string(data).map_err(|e| e.to_string()),
+            SecretFormat::Toml => toml::to_string(data).map_err(|e| e.to_string()),
+            SecretFormat::Yaml => serde_yaml::to_string(data).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for SecretFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SecretFormat::Json => "json",
+            SecretFormat::Toml => "toml",
+            SecretFormat::Yaml => "yaml",
+        })
+    }
+}
+
+impl FromStr for SecretFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(SecretFormat::Json),
+            "toml" => Ok(SecretFormat::Toml),
+            "yaml" | "yml" => Ok(SecretFormat::Yaml),
+            _ => Err(format!("unsupported secret format '{}'", s)),
+        }
+    }
+}
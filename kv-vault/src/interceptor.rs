@@ -0,0 +1,20 @@
+//! Post-processing of secrets after they are read
+//!
+//! A [ReadInterceptor] transforms the value of each secret read by the client, after it is
+//! decompressed and before it is deserialized into the caller's type. Interceptors are set
+//! with [Config::read_interceptors](crate::config::Config::read_interceptors) and run in order,
+//! each receiving the output of the previous one. An error from any interceptor fails the read.
+use std::fmt;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::error::VaultError;
+
+/// Transforms secret values after they are read
+#[async_trait]
+pub trait ReadInterceptor: Send + Sync + fmt::Debug {
+    /// Returns the value to pass to the next interceptor, or to return to the caller.
+    /// `path` is the path of the secret within the mount.
+    async fn intercept(&self, path: &str, value: Value) -> Result<Value, VaultError>;
+}
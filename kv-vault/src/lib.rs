@@ -1,6 +1,24 @@
+pub mod audit;
+pub mod b64;
+pub mod breaker;
+pub mod chunk;
 pub mod client;
+pub mod compression;
 pub mod config;
+pub mod diff;
 pub mod error;
+pub mod format;
+pub mod interceptor;
+pub mod path;
+pub mod pool;
+pub mod router;
+pub mod schema;
+pub mod secret;
+pub mod signer;
+pub mod store;
+pub mod transform;
+pub mod tree;
+pub mod walker;
 
 /// token to indicate string data was passed during set
 pub const STRING_VALUE_MARKER: &str = "string_data___";
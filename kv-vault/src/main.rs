@@ -2,10 +2,13 @@
 //!
 use std::collections::HashMap;
 
-use kv_vault_lib::{client::Client, config::Config, error::VaultError, STRING_VALUE_MARKER};
+use kv_vault_lib::{
+    client::Client, config::Config, error::VaultError, pool::ClientPool, STRING_VALUE_MARKER,
+};
 use serde_json::Value;
 use tokio::sync::RwLock;
-use tracing::{debug, info, instrument};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, instrument, warn};
 use wasmbus_rpc::provider::prelude::*;
 use wasmcloud_interface_keyvalue::{
     GetResponse, IncrementRequest, KeyValue, KeyValueReceiver, ListAddRequest, ListDelRequest,
@@ -29,8 +32,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 #[services(KeyValue)]
 struct KvVaultProvider {
     // store redis connections per actor
-    actors: std::sync::Arc<RwLock<HashMap<String, RwLock<Client>>>>,
+    actors: std::sync::Arc<RwLock<HashMap<String, ActorLink>>>,
+    // connections shared by links to the same vault server with the same token
+    pool: std::sync::Arc<ClientPool>,
+}
+/// The client of a linked actor, and the token that stops its secret age sampler, if any
+struct ActorLink {
+    client: RwLock<Client>,
+    sampler: Option<CancellationToken>,
 }
+
 /// use default implementations of provider message handlers
 impl ProviderDispatch for KvVaultProvider {}
 
@@ -44,10 +55,33 @@ impl ProviderHandler for KvVaultProvider {
     #[instrument(level = "debug", skip(self, ld), fields(actor_id = %ld.actor_id))]
     async fn put_link(&self, ld: &LinkDefinition) -> RpcResult<bool> {
         let config = Config::from_values(&ld.values)?;
-        let client = Client::new(config).map_err(to_rpc_err)?;
+        let (age_paths, age_interval) =
+            (config.secret_age_paths.clone(), config.secret_age_interval);
+        let client = self.pool.client(config).map_err(to_rpc_err)?;
+        let sampler = match age_paths.is_empty() {
+            true => None,
+            false => {
+                let token = CancellationToken::new();
+                let (client, stop) = (client.clone(), token.clone());
+                tokio::spawn(async move {
+                    client
+                        .sample_secret_ages(&age_paths, age_interval, stop)
+                        .await
+                });
+                Some(token)
+            }
+        };
         let mut update_map = self.actors.write().await;
         info!("adding link for actor");
-        update_map.insert(ld.actor_id.to_string(), RwLock::new(client));
+        let link = ActorLink {
+            client: RwLock::new(client),
+            sampler,
+        };
+        if let Some(previous) = update_map.insert(ld.actor_id.to_string(), link) {
+            if let Some(sampler) = previous.sampler {
+                sampler.cancel();
+            }
+        }
         Ok(true)
     }
 
@@ -55,9 +89,9 @@ impl ProviderHandler for KvVaultProvider {
     #[instrument(level = "debug", skip(self))]
     async fn delete_link(&self, actor_id: &str) {
         let mut aw = self.actors.write().await;
-        if let Some(client) = aw.remove(actor_id) {
+        if let Some(link) = aw.remove(actor_id) {
             info!("deleting link for actor");
-            drop(client)
+            close_client(link).await
         }
     }
 
@@ -65,13 +99,28 @@ impl ProviderHandler for KvVaultProvider {
     async fn shutdown(&self) -> Result<(), std::convert::Infallible> {
         let mut aw = self.actors.write().await;
         // empty the actor link data and stop all servers
-        for (_, client) in aw.drain() {
-            drop(client)
+        for (_, link) in aw.drain() {
+            close_client(link).await
         }
         Ok(())
     }
 }
 
+/// Stops the link's secret age sampler and drops its client,
+/// first revoking its token if the link was configured to do so
+async fn close_client(link: ActorLink) {
+    if let Some(sampler) = link.sampler {
+        sampler.cancel();
+    }
+    let client = link.client.into_inner();
+    if client.revoke_on_shutdown() {
+        match client.revoke_self().await {
+            Ok(()) => info!("revoked vault token"),
+            Err(e) => warn!(error = %e, "failed to revoke vault token"),
+        }
+    }
+}
+
 fn to_rpc_err(e: VaultError) -> RpcError {
     RpcError::Other(format!("vault error: {}", e))
 }
@@ -294,10 +343,10 @@ impl KvVaultProvider {
             .ok_or_else(|| RpcError::InvalidParameter("no actor in request".to_string()))?;
         // get read lock on actor-client hashmap
         let rd = self.actors.read().await;
-        let client_rw = rd
+        let link = rd
             .get(actor_id)
             .ok_or_else(|| RpcError::InvalidParameter(format!("actor not linked:{}", actor_id)))?;
-        let client = client_rw.read().await.clone();
+        let client = link.client.read().await.clone();
         Ok(client)
     }
 }
@@ -0,0 +1,89 @@
+//! Normalization of secret paths
+//!
+//! Paths are split on '/' and empty segments are dropped, so that `/app//db/` and `app/db`
+//! refer to the same secret. Paths containing `.` or `..` segments are rejected rather than
+//! resolved, since vault treats them as literal key names.
+//!
+//! Each segment of a normalized path is percent-encoded when it is put in a request url,
+//! so key names containing characters such as spaces, '%', '?', or '#' reach vault unchanged.
+use crate::error::VaultError;
+
+/// Normalizes the path of a secret. Returns `VaultError::InvalidPath` if the path is empty
+/// or contains `.` or `..` segments.
+pub fn normalize(path: &str) -> Result<String, VaultError> {
+    let normalized = normalize_dir(path)?;
+    if normalized.is_empty() {
+        return Err(VaultError::InvalidPath {
+            path: path.to_string(),
+            reason: "path is empty".to_string(),
+        });
+    }
+    Ok(normalized)
+}
+
+/// Normalizes the path of a directory to list. Unlike [normalize], an empty path is allowed,
+/// and refers to the root of the mount.
+pub fn normalize_dir(path: &str) -> Result<String, VaultError> {
+    let mut segments = Vec::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        if segment == "." || segment == ".." {
+            return Err(VaultError::InvalidPath {
+                path: path.to_string(),
+                reason: format!("path contains a '{}' segment", segment),
+            });
+        }
+        segments.push(segment);
+    }
+    Ok(segments.join("/"))
+}
+
+/// Percent-encodes each segment of a normalized path for use in a url, leaving the '/'
+/// separators in place. Letters, digits, and `-._~` are not encoded.
+pub fn encode(path: &str) -> String {
+    encode_bytes(path, b"/")
+}
+
+/// Percent-encodes a single path segment, such as a role name, for use in a url.
+/// Unlike [encode], '/' is encoded too, so the segment can't reach a different endpoint.
+pub fn encode_segment(segment: &str) -> String {
+    encode_bytes(segment, b"")
+}
+
+/// Percent-encodes every byte other than letters, digits, `-._~`, and those in `keep`
+fn encode_bytes(value: &str, keep: &[u8]) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ if keep.contains(&byte) => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Decodes a path encoded by [encode]. Invalid escapes are left as they are.
+pub fn decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,84 @@
+//! Sharing of vault connections between clients
+//!
+//! Each [Client] created with [Client::new] has its own http connection pool.
+//! Applications that use several mounts of the same vault server with the same token can
+//! create their clients from a [ClientPool] instead, so that those clients share one connection.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use url::Url;
+use vaultrs::client::VaultClient;
+
+use crate::{
+    client::{vault_client, Client},
+    config::{Config, TlsVersion},
+    error::VaultError,
+};
+
+/// Settings that identify a connection
+#[derive(PartialEq, Eq, Hash)]
+struct ConnectionKey {
+    address: String,
+    token: String,
+    certs: Vec<String>,
+    pool_idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    min_tls_version: Option<TlsVersion>,
+    follow_redirects: bool,
+}
+
+/// Registry of vault connections shared by the clients created from it.
+///
+/// The pool holds only weak references, so a connection is closed when the last client
+/// using it is dropped, even if the pool is still alive.
+#[derive(Default)]
+pub struct ClientPool {
+    connections: Mutex<HashMap<ConnectionKey, Weak<VaultClient>>>,
+}
+
+impl ClientPool {
+    pub fn new() -> Self {
+        ClientPool::default()
+    }
+
+    /// Creates a client for the config's mount, reusing an existing connection
+    /// for the same address, token, and connection settings if one is still in use.
+    pub fn client(&self, config: Config) -> Result<Client, VaultError> {
+        let inner = self.connection(&config, config.addr.clone())?;
+        let replica = match &config.read_replica_addr {
+            Some(addr) => Some(self.connection(&config, addr.clone())?),
+            None => None,
+        };
+        Ok(Client::with_connections(config, inner, replica))
+    }
+
+    /// Returns the number of connections currently used by clients from this pool
+    pub fn connection_count(&self) -> usize {
+        let mut connections = self.connections.lock().unwrap();
+        connections.retain(|_, conn| conn.strong_count() > 0);
+        connections.len()
+    }
+
+    fn connection(&self, config: &Config, address: Url) -> Result<Arc<VaultClient>, VaultError> {
+        let mut connections = self.connections.lock().unwrap();
+        connections.retain(|_, conn| conn.strong_count() > 0);
+        let key = ConnectionKey {
+            address: address.to_string(),
+            token: config.token.clone(),
+            certs: config.certs.clone(),
+            pool_idle_timeout: config.pool_idle_timeout,
+            tcp_keepalive: config.tcp_keepalive,
+            min_tls_version: config.min_tls_version,
+            follow_redirects: config.follow_redirects,
+        };
+        if let Some(conn) = connections.get(&key).and_then(Weak::upgrade) {
+            return Ok(conn);
+        }
+        let conn = Arc::new(vault_client(config, address)?);
+        connections.insert(key, Arc::downgrade(&conn));
+        Ok(conn)
+    }
+}
@@ -0,0 +1,66 @@
+//! Routing of secret paths to kv mounts
+//!
+//! A [MountRouter] presents several kv mounts as one virtual tree of secrets.
+//! Each route maps a path prefix, such as `prod`, to a mount, and the prefix is removed
+//! from the path before it is sent to that mount, so `prod/app/db` is read as `app/db`
+//! in the mount for `prod`.
+use crate::{error::VaultError, path};
+
+/// Maps path prefixes to kv mounts
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MountRouter {
+    /// (prefix, mount) pairs. Prefixes are normalized paths.
+    routes: Vec<(String, String)>,
+    /// If true, paths that match no route use the client's default mount.
+    /// If false, they are rejected with `VaultError::InvalidPath`.
+    pub fallback: bool,
+}
+
+impl MountRouter {
+    /// Creates a router with no routes
+    pub fn new(fallback: bool) -> Self {
+        MountRouter {
+            routes: Vec::new(),
+            fallback,
+        }
+    }
+
+    /// Adds a route from the prefix to the mount
+    pub fn route_prefix(mut self, prefix: &str, mount: &str) -> Result<Self, VaultError> {
+        self.routes
+            .push((path::normalize(prefix)?, mount.to_string()));
+        Ok(self)
+    }
+
+    /// Returns the mounts of the routes, sorted and without duplicates
+    pub fn mounts(&self) -> Vec<&str> {
+        let mut mounts = self
+            .routes
+            .iter()
+            .map(|(_, mount)| mount.as_str())
+            .collect::<Vec<_>>();
+        mounts.sort_unstable();
+        mounts.dedup();
+        mounts
+    }
+
+    /// Returns the mount and the path within the mount for the longest prefix that matches
+    /// the path, or None if no prefix matches. Prefixes match whole path segments,
+    /// so the prefix `prod` matches `prod/db` but not `production/db`.
+    pub fn route(&self, path: &str) -> Result<Option<(&str, String)>, VaultError> {
+        let path = path::normalize(path)?;
+        let matched = self
+            .routes
+            .iter()
+            .filter_map(|(prefix, mount)| {
+                let rest = path.strip_prefix(prefix.as_str())?;
+                match rest.strip_prefix('/') {
+                    Some(rest) => Some((prefix.len(), mount.as_str(), rest.to_string())),
+                    None if rest.is_empty() => Some((prefix.len(), mount.as_str(), String::new())),
+                    None => None,
+                }
+            })
+            .max_by_key(|(len, _, _)| *len);
+        Ok(matched.map(|(_, mount, rest)| (mount, rest)))
+    }
+}
@@ -0,0 +1,28 @@
+//! Comparison of stored secrets with the types they are read into
+//!
+//! serde ignores fields of a json map that the target type doesn't have, and fills in
+//! defaulted fields that the map doesn't have. [field_diff] finds both, so that strict reads
+//! can detect drift between the writers and readers of a secret.
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Returns the top-level fields of the stored value that are not present when the parsed
+/// value is serialized again (extra fields), and those that are present only after serializing
+/// the parsed value (missing fields). Both lists are sorted.
+/// Values that are not maps have no fields.
+pub fn field_diff<D: Serialize>(stored: &Value, parsed: &D) -> (Vec<String>, Vec<String>) {
+    let round_trip = serde_json::to_value(parsed).unwrap_or(Value::Null);
+    let keys = |value: &Value| -> BTreeSet<String> {
+        value
+            .as_object()
+            .map(|map| map.keys().cloned().collect())
+            .unwrap_or_default()
+    };
+    let (stored, round_trip) = (keys(stored), keys(&round_trip));
+    (
+        stored.difference(&round_trip).cloned().collect(),
+        round_trip.difference(&stored).cloned().collect(),
+    )
+}
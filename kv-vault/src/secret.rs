@@ -0,0 +1,48 @@
+//! Secret strings that are redacted when formatted
+//!
+use std::fmt;
+
+use zeroize::Zeroize;
+
+/// A secret value whose `Debug` and `Display` output is `***`, so that it is not disclosed
+/// when a containing struct or error is logged. The memory is zeroed when it is dropped.
+///
+/// Copies made before the value was wrapped, such as the buffers used to parse the response
+/// from vault, are not zeroed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: String) -> Self {
+        SecretString(value)
+    }
+
+    /// Returns the secret value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        SecretString(value)
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
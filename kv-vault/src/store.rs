@@ -0,0 +1,143 @@
+//! Secret storage abstraction
+//!
+//! [SecretStore] captures the read/write/delete/list surface of [Client](crate::client::Client)
+//! so that code built on this crate can depend on `dyn SecretStore` and substitute
+//! [MockSecretStore] in tests that should not require a running vault server.
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    client::{require_map, Client, WriteMetadata},
+    error::VaultError,
+    path,
+};
+
+/// Operations on secrets in a kv store
+#[async_trait]
+pub trait SecretStore: Send + Sync {
+    /// Reads the latest value of the secret at the path
+    async fn read_secret(&self, path: &str) -> Result<Value, VaultError>;
+
+    /// Writes a new version of the secret at the path
+    async fn write_secret(&self, path: &str, data: &Value) -> Result<WriteMetadata, VaultError>;
+
+    /// Deletes the latest version of the secret at the path
+    async fn delete_latest(&self, path: &str) -> Result<(), VaultError>;
+
+    /// Lists keys at the path
+    async fn list_secrets(&self, path: &str) -> Result<Vec<String>, VaultError>;
+}
+
+#[async_trait]
+impl SecretStore for Client {
+    async fn read_secret(&self, path: &str) -> Result<Value, VaultError> {
+        Client::read_secret(self, path).await
+    }
+
+    async fn write_secret(&self, path: &str, data: &Value) -> Result<WriteMetadata, VaultError> {
+        Client::write_secret(self, path, data).await
+    }
+
+    async fn delete_latest(&self, path: &str) -> Result<(), VaultError> {
+        Client::delete_latest::<Value>(self, path).await
+    }
+
+    async fn list_secrets(&self, path: &str) -> Result<Vec<String>, VaultError> {
+        Client::list_secrets(self, path).await
+    }
+}
+
+/// In-memory [SecretStore] for tests. No network connections are made.
+///
+/// Paths are normalized as they are by [Client](crate::client::Client).
+/// Each write adds a new version, and `delete_latest` soft-deletes the latest version,
+/// so reads of a deleted secret return NotFound while its key is still listed,
+/// as they would with the vault kv v2 engine. Values that are not json maps are rejected
+/// with `VaultError::InvalidData`, as `Client` rejects them.
+#[derive(Debug, Default)]
+pub struct MockSecretStore {
+    mount: String,
+    secrets: Mutex<HashMap<String, Vec<Option<Value>>>>,
+}
+
+impl MockSecretStore {
+    /// Creates an empty store. The mount name is only used in NotFound errors.
+    pub fn new(mount: &str) -> Self {
+        MockSecretStore {
+            mount: mount.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn not_found(&self, path: &str) -> VaultError {
+        VaultError::NotFound {
+            namespace: self.mount.clone(),
+            path: path.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SecretStore for MockSecretStore {
+    async fn read_secret(&self, path: &str) -> Result<Value, VaultError> {
+        let key = path::normalize(path)?;
+        let secrets = self.secrets.lock().unwrap();
+        match secrets.get(&key).and_then(|versions| versions.last()) {
+            Some(Some(value)) => Ok(value.clone()),
+            _ => Err(self.not_found(path)),
+        }
+    }
+
+    async fn write_secret(&self, path: &str, data: &Value) -> Result<WriteMetadata, VaultError> {
+        let key = path::normalize(path)?;
+        require_map(data)?;
+        let mut secrets = self.secrets.lock().unwrap();
+        let versions = secrets.entry(key).or_default();
+        versions.push(Some(data.clone()));
+        Ok(WriteMetadata {
+            version: versions.len() as u64,
+            ..Default::default()
+        })
+    }
+
+    async fn delete_latest(&self, path: &str) -> Result<(), VaultError> {
+        let key = path::normalize(path)?;
+        let mut secrets = self.secrets.lock().unwrap();
+        match secrets
+            .get_mut(&key)
+            .and_then(|versions| versions.last_mut())
+        {
+            Some(latest) => {
+                *latest = None;
+                Ok(())
+            }
+            None => Err(self.not_found(path)),
+        }
+    }
+
+    /// Returns the immediate children of the path. Keys with children are returned
+    /// with a trailing '/', as vault does.
+    async fn list_secrets(&self, path: &str) -> Result<Vec<String>, VaultError> {
+        let prefix = match path::normalize_dir(path)?.as_str() {
+            "" => String::new(),
+            p => format!("{}/", p),
+        };
+        let secrets = self.secrets.lock().unwrap();
+        let mut keys = secrets
+            .keys()
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .map(|rest| match rest.find('/') {
+                Some(pos) => rest[..=pos].to_string(),
+                None => rest.to_string(),
+            })
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return Err(self.not_found(path));
+        }
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+}
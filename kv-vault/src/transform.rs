@@ -0,0 +1,90 @@
+//! Format-preserving encoding with vault's transform secrets engine
+//!
+//! The transform engine replaces sensitive values, such as card numbers, with tokens of the same
+//! format, so they can be stored and passed around without exposing the original value.
+//! Each transformation is configured in vault as a role. The engine is only available in
+//! vault enterprise, and the client checks for an enterprise server before using it.
+use serde::Deserialize;
+
+use crate::{client::Client, error::VaultError, path};
+
+/// Vault enterprise release that added the transform secrets engine
+const MIN_TRANSFORM_VERSION: semver::Version = semver::Version::new(1, 4, 0);
+
+const FEATURE: &str = "the transform secrets engine";
+
+/// Encodes and decodes values with the transform engine at the client's `transform_mount`,
+/// created by [Client::transform]
+pub struct Transform<'a> {
+    client: &'a Client,
+}
+
+impl Client {
+    /// Returns a handle for the transform secrets engine at the configured `transform_mount`
+    pub fn transform(&self) -> Transform<'_> {
+        Transform { client: self }
+    }
+}
+
+impl Transform<'_> {
+    /// Encodes the value with the role's transformation, using `:mount/encode/:role`.
+    /// Returns `VaultError::RequiresEnterprise` if the server is not vault enterprise,
+    /// or `VaultError::UnsupportedByServer` if it is older than 1.4.
+    pub async fn encode(&self, role: &str, value: &str) -> Result<String, VaultError> {
+        #[derive(Deserialize)]
+        struct EncodeResponse {
+            encoded_value: String,
+        }
+
+        let res: EncodeResponse = self.send("encode", role, value).await?;
+        Ok(res.encoded_value)
+    }
+
+    /// Decodes a value encoded by [encode](Transform::encode) with the same role,
+    /// using `:mount/decode/:role`. The role's transformation must be reversible.
+    pub async fn decode(&self, role: &str, value: &str) -> Result<String, VaultError> {
+        #[derive(Deserialize)]
+        struct DecodeResponse {
+            decoded_value: String,
+        }
+
+        let res: DecodeResponse = self.send("decode", role, value).await?;
+        Ok(res.decoded_value)
+    }
+
+    async fn send<R: serde::de::DeserializeOwned>(
+        &self,
+        operation: &str,
+        role: &str,
+        value: &str,
+    ) -> Result<R, VaultError> {
+        #[derive(Deserialize)]
+        struct ApiResponse<T> {
+            data: T,
+        }
+
+        // enterprise servers report versions such as 1.15.2+ent
+        let actual = self.client.server_version().await?;
+        if !actual.build.as_str().starts_with("ent") {
+            return Err(VaultError::RequiresEnterprise {
+                feature: FEATURE.to_string(),
+                actual,
+            });
+        }
+        self.client
+            .require_server_version(FEATURE, &MIN_TRANSFORM_VERSION)
+            .await?;
+        let api_path = format!(
+            "{}/{}/{}",
+            self.client.transform_mount,
+            operation,
+            path::encode_segment(role)
+        );
+        let body = serde_json::json!({ "value": value });
+        let res: ApiResponse<R> = self
+            .client
+            .request(reqwest::Method::POST, &api_path, Some(&body))
+            .await?;
+        Ok(res.data)
+    }
+}
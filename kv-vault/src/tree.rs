@@ -0,0 +1,51 @@
+//! Conversion between nested json trees and secrets at paths
+//!
+//! In a tree, a map whose values are all non-empty maps is a directory, and its keys are
+//! path segments. Any other value is a secret. A secret whose fields are all maps can't be
+//! represented, because it would be read back as a directory.
+use serde_json::{Map, Value};
+
+/// Returns the secrets in the tree, with their paths below the base path, sorted by path.
+/// If the tree itself is a secret, it is returned at the base path.
+pub fn flatten(tree: &Value, base_path: &str) -> Vec<(String, Value)> {
+    let mut secrets = Vec::new();
+    let mut pending = vec![(base_path.trim_matches('/').to_string(), tree)];
+    while let Some((path, value)) = pending.pop() {
+        match value.as_object().filter(|map| is_directory(map)) {
+            Some(map) => pending.extend(map.iter().map(|(key, child)| {
+                let key = key.trim_matches('/');
+                match path.as_str() {
+                    "" => (key.to_string(), child),
+                    _ => (format!("{}/{}", path, key), child),
+                }
+            })),
+            None => secrets.push((path, value.clone())),
+        }
+    }
+    secrets.sort_by(|a, b| a.0.cmp(&b.0));
+    secrets
+}
+
+/// Adds the secret to the tree at the path, creating directories as needed.
+/// A secret already at a directory's place is replaced by the directory.
+pub fn insert(tree: &mut Value, path: &str, secret: Value) {
+    let mut node = tree;
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        if !node.as_object().map(is_directory).unwrap_or(false) {
+            *node = Value::Object(Map::new());
+        }
+        node = node
+            .as_object_mut()
+            .unwrap()
+            .entry(segment.to_string())
+            .or_insert(Value::Null);
+    }
+    *node = secret;
+}
+
+fn is_directory(map: &Map<String, Value>) -> bool {
+    !map.is_empty()
+        && map
+            .values()
+            .all(|v| v.as_object().map(|m| !m.is_empty()).unwrap_or(false))
+}
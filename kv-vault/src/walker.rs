@@ -0,0 +1,131 @@
+//! Lazy traversal of the keys below a path
+//!
+//! A [SecretWalker] lists one directory at a time, as the caller advances it, so a caller can
+//! stop early or skip directories it doesn't need without listing the whole tree.
+use std::collections::VecDeque;
+
+use crate::{client::Client, error::VaultError, path};
+
+/// A key or directory found by a [SecretWalker]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalkEntry {
+    /// Path of the key or directory within the mount, without a trailing '/'
+    pub path: String,
+    /// Number of directories between the start path and the entry.
+    /// Entries directly below the start path have depth 0.
+    pub depth: usize,
+    pub is_dir: bool,
+}
+
+/// A directory being walked, whose entries are listed when it is first reached
+struct Frame {
+    dir: String,
+    depth: usize,
+    entries: Option<VecDeque<String>>,
+}
+
+/// Depth-first walk of the keys and directories below a path, created by [Client::walk].
+/// Each directory is yielded before its contents, and its contents are listed
+/// when the walker next advances, unless [skip_current_dir](SecretWalker::skip_current_dir)
+/// is called first.
+pub struct SecretWalker<'a> {
+    client: &'a Client,
+    stack: Vec<Frame>,
+    /// The directory yielded last, which is entered on the next call to next
+    descend: Option<Frame>,
+    keys_visited: usize,
+}
+
+impl Client {
+    /// Returns a walker over the keys and directories below the path.
+    /// An empty path walks the whole mount.
+    pub fn walk(&self, path: &str) -> Result<SecretWalker<'_>, VaultError> {
+        let dir = match path::normalize_dir(path)?.as_str() {
+            "" => String::new(),
+            p => format!("{}/", p),
+        };
+        Ok(SecretWalker {
+            client: self,
+            stack: Vec::new(),
+            descend: Some(Frame {
+                dir,
+                depth: 0,
+                entries: None,
+            }),
+            keys_visited: 0,
+        })
+    }
+}
+
+impl SecretWalker<'_> {
+    /// Returns the next entry, or None when the walk is complete. A directory that doesn't exist
+    /// is treated as empty. If listing a directory fails, the error is returned and the
+    /// directory is skipped, so the walk can continue.
+    #[allow(clippy::should_implement_trait)]
+    pub async fn next(&mut self) -> Option<Result<WalkEntry, VaultError>> {
+        loop {
+            if let Some(frame) = self.descend.take() {
+                self.stack.push(frame);
+            }
+            let frame = self.stack.last_mut()?;
+            if frame.entries.is_none() {
+                match self.client.list_secrets(&frame.dir).await {
+                    Ok(keys) => frame.entries = Some(keys.into()),
+                    Err(VaultError::NotFound { .. }) => frame.entries = Some(VecDeque::new()),
+                    Err(e) => {
+                        self.stack.pop();
+                        return Some(Err(e));
+                    }
+                }
+            }
+            let entry = match frame.entries.as_mut().and_then(VecDeque::pop_front) {
+                Some(entry) => entry,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let path = format!("{}{}", frame.dir, entry);
+            let depth = frame.depth;
+            if entry.ends_with('/') {
+                self.descend = Some(Frame {
+                    dir: path.clone(),
+                    depth: depth + 1,
+                    entries: None,
+                });
+                return Some(Ok(WalkEntry {
+                    path: path.trim_end_matches('/').to_string(),
+                    depth,
+                    is_dir: true,
+                }));
+            }
+            self.keys_visited += 1;
+            return Some(Ok(WalkEntry {
+                path,
+                depth,
+                is_dir: false,
+            }));
+        }
+    }
+
+    /// Skips the contents of the directory yielded last. If the entry yielded last
+    /// was a key, skips the rest of the directory containing it.
+    pub fn skip_current_dir(&mut self) {
+        if self.descend.take().is_none() {
+            self.stack.pop();
+        }
+    }
+
+    /// Returns the depth of the directory being walked, where the start path has depth 0
+    pub fn depth(&self) -> usize {
+        self.stack
+            .last()
+            .map(|frame| frame.depth)
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of keys, not counting directories, yielded so far
+    pub fn keys_visited(&self) -> usize {
+        self.keys_visited
+    }
+}
@@ -0,0 +1,43 @@
+//! Tests automatic decoding of base64 fields
+//!
+use kv_vault_lib::b64::{decode_fields, encode_fields};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[test]
+fn decode_encode_fields() {
+    #[derive(Deserialize)]
+    struct Cert {
+        name: String,
+        der_b64: Vec<u8>,
+    }
+
+    let stored = json!({ "name": "ca", "der_b64": "AAEC/w==" });
+    let mut map = stored.as_object().unwrap().clone();
+    decode_fields(&mut map).unwrap();
+    let cert: Cert = serde_json::from_value(Value::Object(map.clone())).unwrap();
+    assert_eq!(cert.name, "ca");
+    assert_eq!(cert.der_b64, vec![0, 1, 2, 255]);
+
+    encode_fields(&mut map);
+    assert_eq!(Value::Object(map), stored);
+}
+
+#[test]
+fn invalid_field_named() {
+    let mut map = json!({ "ok_b64": "AA==", "bad_b64": "not base64!" })
+        .as_object()
+        .unwrap()
+        .clone();
+    assert_eq!(decode_fields(&mut map).unwrap_err(), "bad_b64");
+
+    let mut map = json!({ "text_b64": "AA==", "other": [1, 2] })
+        .as_object()
+        .unwrap()
+        .clone();
+    encode_fields(&mut map);
+    assert_eq!(
+        Value::Object(map),
+        json!({ "text_b64": "AA==", "other": [1, 2] })
+    );
+}
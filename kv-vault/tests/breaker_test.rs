@@ -0,0 +1,47 @@
+//! Tests the circuit breaker
+//!
+use std::time::Duration;
+
+use kv_vault_lib::{breaker::CircuitBreaker, error::VaultError};
+
+#[test]
+fn opens_after_threshold() {
+    let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+    for _ in 0..2 {
+        breaker.allow().unwrap();
+        breaker.record(true);
+    }
+    // a success resets the count
+    breaker.allow().unwrap();
+    breaker.record(false);
+    for _ in 0..3 {
+        breaker.allow().unwrap();
+        breaker.record(true);
+    }
+    assert!(matches!(
+        breaker.allow(),
+        Err(VaultError::CircuitOpen { .. })
+    ));
+}
+
+#[test]
+fn probes_after_cooldown() {
+    let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+    breaker.allow().unwrap();
+    breaker.record(true);
+    assert!(breaker.allow().is_err());
+
+    std::thread::sleep(Duration::from_millis(30));
+    // only one probe is allowed
+    breaker.allow().unwrap();
+    assert!(breaker.allow().is_err());
+    // failed probe opens the breaker again
+    breaker.record(true);
+    assert!(breaker.allow().is_err());
+
+    std::thread::sleep(Duration::from_millis(30));
+    breaker.allow().unwrap();
+    breaker.record(false);
+    breaker.allow().unwrap();
+    breaker.allow().unwrap();
+}
@@ -0,0 +1,39 @@
+//! Tests splitting and joining large secrets
+//!
+use kv_vault_lib::chunk::{join, split, CHUNK_BYTES};
+
+#[test]
+fn split_join() {
+    let data = (0..CHUNK_BYTES * 2 + 10)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<_>>();
+    let (manifest, chunks) = split(&data);
+    assert_eq!(manifest.size, data.len());
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(manifest.chunks.len(), 3);
+
+    let owned = chunks.iter().map(|c| Some(c.to_vec())).collect();
+    assert_eq!(join(&manifest, owned).unwrap(), data);
+
+    let (empty, chunks) = split(&[]);
+    assert!(chunks.is_empty());
+    assert_eq!(join(&empty, Vec::new()).unwrap(), Vec::<u8>::new());
+}
+
+#[test]
+fn join_errors() {
+    let data = vec![7u8; CHUNK_BYTES + 1];
+    let (manifest, chunks) = split(&data);
+
+    let missing = vec![Some(chunks[0].to_vec()), None];
+    assert!(join(&manifest, missing)
+        .unwrap_err()
+        .contains("chunk-1 is missing"));
+
+    let mut corrupt = chunks[1].to_vec();
+    corrupt[0] = 0;
+    let corrupted = vec![Some(chunks[0].to_vec()), Some(corrupt)];
+    assert!(join(&manifest, corrupted).unwrap_err().contains("chunk-1"));
+
+    assert!(join(&manifest, vec![Some(chunks[0].to_vec())]).is_err());
+}
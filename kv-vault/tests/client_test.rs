@@ -0,0 +1,1726 @@
+//! Tests client operations that don't require a vault server
+//!
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use kv_vault_lib::{
+    audit::{AuditEvent, AuditOperation, AuditOutcome, AuditSink},
+    client::{
+        Client, DeleteMode, KvVersion, ListOpts, CLIENT_ID_FIELD, WRITTEN_AT_FIELD,
+        WRITTEN_BY_FIELD,
+    },
+    config::Config,
+    error::VaultError,
+};
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+
+fn client() -> Client {
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        // nothing should be listening on the discard port
+        ("addr".to_string(), "http://127.0.0.1:9".to_string()),
+    ]);
+    Client::new(Config::from_values(&values).unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn read_cancelled() {
+    let token = CancellationToken::new();
+    token.cancel();
+    assert!(matches!(
+        client()
+            .read_secret_cancellable::<Value>("app/db", token)
+            .await,
+        Err(VaultError::Cancelled { .. })
+    ));
+}
+
+#[tokio::test]
+async fn request_timeout() {
+    // the listener accepts connections but never responds
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        (
+            "addr".to_string(),
+            format!("http://{}", listener.local_addr().unwrap()),
+        ),
+        ("request_timeout".to_string(), "1".to_string()),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+    match client.read_secret::<Value>("app/db").await {
+        Err(VaultError::Timeout { operation }) => assert!(operation.contains("secret/data/app/db")),
+        other => panic!("expected timeout, got {:?}", other),
+    }
+}
+
+/// An http request received by [serve_with]
+#[derive(Clone, Debug)]
+struct Request {
+    /// The request line, such as `GET /v1/secret/data/app HTTP/1.1`
+    line: String,
+    /// Header names, in lowercase, and values
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+impl Request {
+    fn method(&self) -> &str {
+        self.line.split(' ').next().unwrap_or_default()
+    }
+
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+/// Serves http requests on the listener in a background thread, one per connection,
+/// recording each request and answering with the response for it
+fn serve_with(
+    listener: TcpListener,
+    respond: impl Fn(&Request) -> String + Send + 'static,
+) -> Arc<Mutex<Vec<Request>>> {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut headers = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                }
+            }
+            let content_length = headers
+                .get("content-length")
+                .map(|len| len.parse().unwrap())
+                .unwrap_or(0);
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let request = Request {
+                line: request_line.trim().to_string(),
+                headers,
+                body: String::from_utf8(body).unwrap(),
+            };
+            let response = respond(&request);
+            recorded.lock().unwrap().push(request);
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    requests
+}
+
+/// Like [serve_with], recording and answering only the request line of each request
+fn serve(
+    listener: TcpListener,
+    respond: impl Fn(&str) -> String + Send + 'static,
+) -> Arc<Mutex<Vec<String>>> {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let recorded = lines.clone();
+    serve_with(listener, move |request| {
+        recorded.lock().unwrap().push(request.line.clone());
+        respond(&request.line)
+    });
+    lines
+}
+
+/// Returns an http response with a json body
+fn response(status: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// Returns the body of a kv v2 read of the version with the data
+fn read_body(data: Value, version: u64) -> String {
+    serde_json::json!({
+        "data": {
+            "data": data,
+            "metadata": {
+                "version": version,
+                "created_time": "2022-01-01T00:00:00Z",
+                "deletion_time": "",
+                "destroyed": false,
+            },
+        },
+    })
+    .to_string()
+}
+
+/// Returns the body of a kv v2 write of the version
+fn write_body(version: u64) -> String {
+    serde_json::json!({
+        "data": { "version": version, "created_time": "2022-01-01T00:00:00Z" },
+    })
+    .to_string()
+}
+
+/// Returns the response to a kv v2 write rejected by check-and-set
+fn cas_mismatch() -> String {
+    response(
+        "400 Bad Request",
+        r#"{"errors":["check-and-set parameter did not match the current version"]}"#,
+    )
+}
+
+/// Returns a client of the server at the address, with the settings
+fn server_client(addr: std::net::SocketAddr, settings: &[(&str, &str)]) -> Client {
+    let mut values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+        ("kv_version".to_string(), "2".to_string()),
+    ]);
+    for (key, value) in settings {
+        values.insert(key.to_string(), value.to_string());
+    }
+    Client::new(Config::from_values(&values).unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn write_follows_standby_redirect() {
+    let active = TcpListener::bind("127.0.0.1:0").unwrap();
+    let active_addr = active.local_addr().unwrap();
+    let active_requests = serve(active, |_| {
+        let body = r#"{"data":{"version":1,"created_time":"2022-01-01T00:00:00Z"}}"#;
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    });
+    let standby = TcpListener::bind("127.0.0.1:0").unwrap();
+    let standby_addr = standby.local_addr().unwrap();
+    let standby_requests = serve(standby, move |request| {
+        let path = request.split(' ').nth(1).unwrap_or("/");
+        format!(
+            "HTTP/1.1 307 Temporary Redirect\r\nlocation: http://{}{}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            active_addr, path
+        )
+    });
+
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", standby_addr)),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+    let data = serde_json::json!({ "password": "x" });
+    assert_eq!(
+        client.write_secret("app/db", &data).await.unwrap().version,
+        1
+    );
+    assert_eq!(
+        client.write_secret("app/db", &data).await.unwrap().version,
+        1
+    );
+
+    assert_eq!(
+        *standby_requests.lock().unwrap(),
+        vec!["POST /v1/secret/data/app/db HTTP/1.1"]
+    );
+    assert_eq!(active_requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn not_yet_valid_is_clock_skew() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let date = httpdate::fmt_http_date(std::time::SystemTime::now() + Duration::from_secs(3600));
+    serve(listener, move |_| {
+        let body = r#"{"errors":["token not yet valid"]}"#;
+        format!(
+            "HTTP/1.1 403 Forbidden\r\ndate: {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            date,
+            body.len(),
+            body
+        )
+    });
+
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+    match client.read_secret::<Value>("app/db").await {
+        Err(VaultError::ClockSkew { hint }) => assert!(hint.contains("ahead"), "{}", hint),
+        other => panic!("expected clock skew, got {:?}", other),
+    }
+}
+
+#[derive(Debug, Default)]
+struct RecordingSink(Mutex<Vec<AuditEvent>>);
+
+#[async_trait]
+impl AuditSink for RecordingSink {
+    async fn record(&self, event: AuditEvent) -> Result<(), String> {
+        self.0.lock().unwrap().push(event);
+        Err("sink failures are only logged".to_string())
+    }
+}
+
+#[tokio::test]
+async fn audit_events() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    serve(listener, |request| {
+        let (status, body) = match request.split(' ').collect::<Vec<_>>()[..2] {
+            ["POST", _] => (
+                "200 OK",
+                r#"{"data":{"version":1,"created_time":"2022-01-01T00:00:00Z"}}"#,
+            ),
+            ["GET", "/v1/secret/metadata/my%20app/db"] => (
+                "200 OK",
+                r#"{"data":{"cas_required":false,"created_time":"2022-01-01T00:00:00Z","current_version":1,"delete_version_after":"0s","max_versions":0,"oldest_version":0,"updated_time":"2022-01-01T00:00:00Z","custom_metadata":null,"versions":{}}}"#,
+            ),
+            _ => ("404 Not Found", r#"{"errors":[]}"#),
+        };
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    });
+    // nothing listens on this address, so requests to it fail to connect
+    let unreachable = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let sink = Arc::new(RecordingSink::default());
+    let audited_client = |addr: std::net::SocketAddr, settings: &[(&str, &str)]| {
+        let mut values = HashMap::from([
+            ("token".to_string(), "test".to_string()),
+            ("kv_version".to_string(), "2".to_string()),
+            ("addr".to_string(), format!("http://{}", addr)),
+            ("client_id".to_string(), "provider-1".to_string()),
+        ]);
+        for (key, value) in settings {
+            values.insert(key.to_string(), value.to_string());
+        }
+        let mut config = Config::from_values(&values).unwrap();
+        config.audit_sink = Some(sink.clone());
+        Client::new(config).unwrap()
+    };
+    let client = audited_client(addr, &[]);
+
+    client
+        .with_correlation_id(Some("flow-1"))
+        .write_secret("my app/db", &serde_json::json!({ "password": "x" }))
+        .await
+        .unwrap();
+    assert!(client.read_secret::<Value>("my app/db").await.is_err());
+
+    client
+        .delete_if_version("my app/db", 1, DeleteMode::Soft)
+        .await
+        .unwrap();
+
+    let read_only = audited_client(addr, &[("read_only", "true")]);
+    assert!(matches!(
+        read_only
+            .write_secret("my app/db", &serde_json::json!({ "password": "x" }))
+            .await,
+        Err(VaultError::ReadOnly { .. })
+    ));
+
+    let broken = audited_client(unreachable, &[("circuit_breaker_threshold", "1")]);
+    assert!(broken.read_secret::<Value>("my app/db").await.is_err());
+    assert!(matches!(
+        broken.read_secret::<Value>("my app/db").await,
+        Err(VaultError::CircuitOpen { .. })
+    ));
+
+    let events = sink.0.lock().unwrap();
+    assert_eq!(events.len(), 7);
+    assert_eq!(events[0].operation, AuditOperation::Write);
+    assert_eq!(events[0].path, "my app/db");
+    assert_eq!(events[0].client_id.as_deref(), Some("provider-1"));
+    assert_eq!(events[0].correlation_id.as_deref(), Some("flow-1"));
+    assert_eq!(events[0].outcome, AuditOutcome::Success);
+    assert_eq!(events[1].correlation_id, None);
+    assert_eq!(events[1].operation, AuditOperation::Read);
+    assert!(matches!(events[1].outcome, AuditOutcome::Failure(_)));
+    assert_eq!(events[2].operation, AuditOperation::Read);
+    assert!(events[2].metadata);
+    assert_eq!(events[3].operation, AuditOperation::Delete);
+    assert_eq!(events[3].path, "my app/db");
+    assert_eq!(events[3].outcome, AuditOutcome::Success);
+    assert_eq!(events[4].operation, AuditOperation::Write);
+    assert!(matches!(events[4].outcome, AuditOutcome::Refused(_)));
+    assert!(matches!(events[5].outcome, AuditOutcome::Failure(_)));
+    assert!(matches!(events[6].outcome, AuditOutcome::Refused(_)));
+    assert!(!format!("{:?}", events).contains("password"));
+}
+
+#[test]
+fn validate_write() {
+    let client = client();
+    client
+        .validate_write(&serde_json::json!({ "password": "x" }))
+        .unwrap();
+    match client.validate_write(&vec!["a", "b"]) {
+        Err(VaultError::InvalidData { reason }) => {
+            assert!(reason.contains("an array"), "{}", reason)
+        }
+        other => panic!("expected invalid data, got {:?}", other),
+    }
+    let large = serde_json::json!({ "blob": "x".repeat(kv_vault_lib::client::MAX_WRITE_BYTES) });
+    assert!(matches!(
+        client.validate_write(&large),
+        Err(VaultError::InvalidData { .. })
+    ));
+}
+
+#[tokio::test]
+async fn concurrency_limit_queue_timeout() {
+    // the listener accepts connections but never responds
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        (
+            "addr".to_string(),
+            format!("http://{}", listener.local_addr().unwrap()),
+        ),
+        ("max_concurrent_requests".to_string(), "1".to_string()),
+        ("request_queue_timeout".to_string(), "1".to_string()),
+    ]);
+    let sink = Arc::new(RecordingSink::default());
+    let mut config = Config::from_values(&values).unwrap();
+    config.audit_sink = Some(sink.clone());
+    let client = Client::new(config).unwrap();
+    let blocked = client.clone();
+    let first = tokio::spawn(async move { blocked.read_secret::<Value>("app/a").await });
+    while client.in_flight() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    match client.read_secret::<Value>("app/b").await {
+        Err(VaultError::Timeout { operation }) => {
+            assert!(operation.contains("queued"), "{}", operation)
+        }
+        other => panic!("expected timeout, got {:?}", other),
+    }
+    assert_eq!(client.in_flight(), 1);
+    // the queued request is audited, the one in flight hasn't finished
+    let events = sink.0.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].path, "app/b");
+    assert!(matches!(&events[0].outcome, AuditOutcome::Failure(e) if e.contains("queued")));
+    drop(events);
+    first.abort();
+}
+
+#[test]
+fn generated_correlation_ids_differ() {
+    let client = client();
+    assert_eq!(client.correlation_id(), None);
+    let a = client.with_correlation_id(None);
+    let b = client.with_correlation_id(None);
+    assert!(a.correlation_id().is_some());
+    assert_ne!(a.correlation_id(), b.correlation_id());
+}
+
+#[tokio::test]
+async fn walker_skips_dirs() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |request| {
+        let path = request.split(' ').nth(1).unwrap_or_default();
+        let path = path.split('?').next().unwrap().trim_end_matches('/');
+        let (status, body) = match path {
+            "/v1/secret/metadata/root" => ("200 OK", r#"{"data":{"keys":["a","b/","c/"]}}"#),
+            "/v1/secret/metadata/root/b" => ("200 OK", r#"{"data":{"keys":["x","y/"]}}"#),
+            "/v1/secret/metadata/root/b/y" => ("200 OK", r#"{"data":{"keys":["z"]}}"#),
+            "/v1/secret/metadata/root/c" => ("200 OK", r#"{"data":{"keys":["skipped"]}}"#),
+            _ => ("404 Not Found", r#"{"errors":[]}"#),
+        };
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+
+    let mut walker = client.walk("/root/").unwrap();
+    let mut visited = Vec::new();
+    while let Some(entry) = walker.next().await {
+        let entry = entry.unwrap();
+        visited.push((entry.path.clone(), entry.depth, entry.is_dir));
+        if entry.path == "root/c" {
+            walker.skip_current_dir();
+        }
+        if entry.path == "root/b/y/z" {
+            assert_eq!(walker.depth(), 2);
+        }
+    }
+    assert_eq!(
+        visited,
+        vec![
+            ("root/a".to_string(), 0, false),
+            ("root/b".to_string(), 0, true),
+            ("root/b/x".to_string(), 1, false),
+            ("root/b/y".to_string(), 1, true),
+            ("root/b/y/z".to_string(), 2, false),
+            ("root/c".to_string(), 0, true),
+        ]
+    );
+    assert_eq!(walker.keys_visited(), 3);
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn write_secret_verified() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |request| {
+        let body = match request.split(' ').next() {
+            Some("POST") => r#"{"data":{"version":3,"created_time":"2022-01-01T00:00:00Z"}}"#,
+            _ if request.contains("/good?version=3") => {
+                r#"{"data":{"data":{"password":"x"},"metadata":{"version":3,"created_time":"2022-01-01T00:00:00Z"}}}"#
+            }
+            _ => {
+                r#"{"data":{"data":{"password":"y"},"metadata":{"version":3,"created_time":"2022-01-01T00:00:00Z"}}}"#
+            }
+        };
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+    let data = HashMap::from([("password".to_string(), "x".to_string())]);
+
+    let written = client
+        .write_secret_verified("app/good", &data)
+        .await
+        .unwrap();
+    assert_eq!(written.version, 3);
+    match client.write_secret_verified("app/bad", &data).await {
+        Err(VaultError::VerificationFailed { path, version, .. }) => {
+            assert_eq!((path.as_str(), version), ("app/bad", 3))
+        }
+        other => panic!("expected verification failure, got {:?}", other),
+    }
+    assert!(requests.lock().unwrap()[1].contains("?version=3"));
+}
+
+#[tokio::test]
+async fn read_only_refuses_writes() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |_| {
+        let body = r#"{"data":{"data":{"password":"x"},"metadata":{"version":1,"created_time":"2022-01-01T00:00:00Z","deletion_time":"","destroyed":false}}}"#;
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+        ("read_only".to_string(), "true".to_string()),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+    let data = serde_json::json!({ "password": "y" });
+
+    assert!(matches!(
+        client.write_secret("app/db", &data).await,
+        Err(VaultError::ReadOnly { .. })
+    ));
+    assert!(matches!(
+        client.delete_latest::<Value>("app/db").await,
+        Err(VaultError::ReadOnly { .. })
+    ));
+    assert!(requests.lock().unwrap().is_empty());
+    assert_eq!(
+        client.read_secret::<Value>("app/db").await.unwrap(),
+        serde_json::json!({ "password": "x" })
+    );
+}
+
+#[tokio::test]
+async fn token_ttl_cached() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |_| {
+        let body = r#"{"data":{"policies":["default"],"ttl":3600}}"#;
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+
+    let ttl = client.token_ttl().await.unwrap().unwrap();
+    assert!(ttl <= Duration::from_secs(3600) && ttl > Duration::from_secs(3590));
+    assert_eq!(client.policies().await.unwrap(), vec!["default"]);
+    assert!(client.token_ttl().await.unwrap().unwrap() <= ttl);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn detect_route_versions() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    serve(listener, |request| {
+        let (status, body) = match request.split(' ').nth(1).unwrap_or_default() {
+            "/v1/sys/internal/ui/mounts/kv-old" => {
+                ("200 OK", r#"{"data":{"options":{"version":"1"}}}"#)
+            }
+            "/v1/sys/internal/ui/mounts/kv-new" => {
+                ("200 OK", r#"{"data":{"options":{"version":"2"}}}"#)
+            }
+            _ => ("500 Internal Server Error", r#"{"errors":["boom"]}"#),
+        };
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+        (
+            "mount_routes".to_string(),
+            "old=kv-old,new=kv-new,broken=kv-broken".to_string(),
+        ),
+        ("mount_routes_fallback".to_string(), "false".to_string()),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+
+    let versions = client.detect_route_versions().await;
+    assert_eq!(versions.len(), 3);
+    assert_eq!(versions["kv-old"].as_ref().unwrap(), &KvVersion::V1);
+    assert_eq!(versions["kv-new"].as_ref().unwrap(), &KvVersion::V2);
+    assert!(versions["kv-broken"].is_err());
+    assert_eq!(
+        client.kv_versions(),
+        HashMap::from([
+            ("kv-old".to_string(), KvVersion::V1),
+            ("kv-new".to_string(), KvVersion::V2),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn secret_ages() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    serve(listener, |request| {
+        let (status, body) = match request.split(' ').nth(1).unwrap_or_default() {
+            "/v1/secret/metadata/app/db" => (
+                "200 OK",
+                r#"{"data":{"cas_required":false,"created_time":"2020-01-01T00:00:00Z","current_version":2,"delete_version_after":"0s","max_versions":0,"oldest_version":0,"updated_time":"2020-01-02T00:00:00.123456Z","custom_metadata":null,"versions":{"1":{"created_time":"2020-01-01T00:00:00Z","deletion_time":"","destroyed":false},"2":{"created_time":"2020-01-02T00:00:00.123456Z","deletion_time":"","destroyed":false}}}}"#,
+            ),
+            _ => ("404 Not Found", r#"{"errors":[]}"#),
+        };
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+
+    let ages = client.secret_ages(&["app/db", "app/missing"]).await;
+    assert_eq!(ages[0].0, "app/db");
+    let created = std::time::UNIX_EPOCH + Duration::from_secs(1_577_923_200);
+    let expected = std::time::SystemTime::now()
+        .duration_since(created)
+        .unwrap();
+    let age = ages[0].1.as_ref().unwrap();
+    assert!(
+        expected.saturating_sub(*age) < Duration::from_secs(5),
+        "{:?}",
+        age
+    );
+    assert_eq!(ages[1].0, "app/missing");
+    assert!(matches!(ages[1].1, Err(VaultError::NotFound { .. })));
+}
+
+fn transform_server(version: &'static str) -> (std::net::SocketAddr, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, move |request| {
+        let body = match request.split(' ').nth(1).unwrap_or_default() {
+            "/v1/sys/seal-status" => format!(r#"{{"version":"{}"}}"#, version),
+            "/v1/fpe/encode/card%20numbers" => {
+                r#"{"data":{"encoded_value":"1111-2222"}}"#.to_string()
+            }
+            "/v1/fpe/decode/card%20numbers" => {
+                r#"{"data":{"decoded_value":"4111-1111"}}"#.to_string()
+            }
+            _ => r#"{"errors":[]}"#.to_string(),
+        };
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    });
+    (addr, requests)
+}
+
+#[tokio::test]
+async fn transform_encode_decode() {
+    let (addr, requests) = transform_server("1.15.2+ent");
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+        ("transform_mount".to_string(), "/fpe/".to_string()),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+    let transform = client.transform();
+
+    assert_eq!(
+        transform.encode("card numbers", "4111-1111").await.unwrap(),
+        "1111-2222"
+    );
+    assert_eq!(
+        transform.decode("card numbers", "1111-2222").await.unwrap(),
+        "4111-1111"
+    );
+    // the server version is read once
+    assert_eq!(requests.lock().unwrap().len(), 3);
+
+    let (addr, requests) = transform_server("1.15.2");
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+        ("transform_mount".to_string(), "fpe".to_string()),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+    match client.transform().encode("card numbers", "4111-1111").await {
+        Err(e @ VaultError::RequiresEnterprise { .. }) => {
+            assert!(e.to_string().contains("requires Vault Enterprise"), "{}", e)
+        }
+        other => panic!("expected requires enterprise, got {:?}", other),
+    }
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn transform_allowed_when_read_only() {
+    let (addr, _) = transform_server("1.15.2+ent");
+    let client = server_client(addr, &[("transform_mount", "fpe"), ("read_only", "true")]);
+
+    assert_eq!(
+        client
+            .transform()
+            .encode("card numbers", "4111-1111")
+            .await
+            .unwrap(),
+        "1111-2222"
+    );
+    assert_eq!(
+        client
+            .transform()
+            .decode("card numbers", "1111-2222")
+            .await
+            .unwrap(),
+        "4111-1111"
+    );
+    assert!(matches!(
+        client
+            .write_secret("fpe/encode/x", &serde_json::json!({ "a": 1 }))
+            .await,
+        Err(VaultError::ReadOnly { .. })
+    ));
+}
+
+#[tokio::test]
+async fn swap_secrets_rolls_back() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |request| {
+        let read = |version| {
+            format!(
+                r#"{{"data":{{"data":{{"v":"x"}},"metadata":{{"version":{},"created_time":"2022-01-01T00:00:00Z","deletion_time":"","destroyed":false}}}}}}"#,
+                version
+            )
+        };
+        let (status, body) = match request.split_once(' ').unwrap() {
+            ("GET", path) if path.starts_with("/v1/secret/data/blue ") => ("200 OK", read(1)),
+            ("GET", _) => ("200 OK", read(4)),
+            ("POST", path) if path.starts_with("/v1/secret/data/broken ") => (
+                "500 Internal Server Error",
+                r#"{"errors":["boom"]}"#.to_string(),
+            ),
+            _ => (
+                "200 OK",
+                r#"{"data":{"version":2,"created_time":"2022-01-01T00:00:00Z"}}"#.to_string(),
+            ),
+        };
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+
+    client.swap_secrets("blue", "green").await.unwrap();
+    assert!(client.swap_secrets("blue", "broken").await.is_err());
+    let methods = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|r| r.split(' ').take(2).collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        vec![
+            "GET /v1/secret/data/blue",
+            "GET /v1/secret/data/green",
+            "POST /v1/secret/data/blue",
+            "POST /v1/secret/data/green",
+            "GET /v1/secret/data/blue",
+            "GET /v1/secret/data/broken",
+            "POST /v1/secret/data/blue",
+            "POST /v1/secret/data/broken",
+            // rollback
+            "POST /v1/secret/data/blue",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn explain_permission_denied() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |request| {
+        let (status, body) = match request.split(' ').nth(1).unwrap_or_default() {
+            "/v1/sys/capabilities-self" => ("200 OK", r#"{"capabilities":["list"]}"#),
+            _ => ("403 Forbidden", r#"{"errors":["permission denied"]}"#),
+        };
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+        ("explain_permission_denied".to_string(), "true".to_string()),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+
+    let err = client.read_secret::<Value>("my app/db").await.unwrap_err();
+    assert!(err.to_string().ends_with("policy needs read"), "{}", err);
+    match err {
+        VaultError::PermissionDenied { path, missing, .. } => {
+            assert_eq!(path, "my app/db");
+            assert_eq!(missing, vec!["read"]);
+        }
+        other => panic!("expected permission denied, got {:?}", other),
+    }
+    let err = client
+        .write_secret("app/db", &serde_json::json!({ "password": "x" }))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        VaultError::PermissionDenied { missing, .. } if missing == vec!["create", "update"]
+    ));
+    assert_eq!(requests.lock().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn list_secrets_with_opts() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    serve(listener, |_| {
+        let body = r#"{"data":{"keys":["foo/","b","foo-bar","a/","foo","b"]}}"#;
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+
+    let list = |sort, dedup| client.list_secrets_with("app", ListOpts { sort, dedup });
+    assert_eq!(
+        list(false, false).await.unwrap(),
+        vec!["foo/", "b", "foo-bar", "a/", "foo", "b"]
+    );
+    assert_eq!(
+        list(true, false).await.unwrap(),
+        vec!["a/", "b", "b", "foo", "foo/", "foo-bar"]
+    );
+    assert_eq!(
+        list(false, true).await.unwrap(),
+        vec!["b", "foo-bar", "a/", "foo"]
+    );
+    assert_eq!(
+        list(true, true).await.unwrap(),
+        vec!["a/", "b", "foo", "foo-bar"]
+    );
+}
+
+#[tokio::test]
+async fn ping() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |request| {
+        let status = match request.split(' ').nth(1) {
+            Some("/v1/sys/health?standbyok=true&perfstandbyok=true") => "200 OK",
+            _ => "503 Service Unavailable",
+        };
+        format!(
+            "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            status
+        )
+    });
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("kv_version".to_string(), "2".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+    ]);
+    let client = Client::new(Config::from_values(&values).unwrap()).unwrap();
+
+    let rtt = client.ping().await.unwrap();
+    assert!(rtt > Duration::ZERO && rtt < Duration::from_secs(5));
+    assert!(requests.lock().unwrap()[0].starts_with("HEAD "));
+}
+
+#[tokio::test]
+async fn write_if_etag_matches_stale() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    // the secret was written again, as version 3, after version 2 was read
+    let requests = serve_with(listener, |request| match request.method() {
+        "GET" => response("200 OK", &read_body(serde_json::json!({ "v": 1 }), 2)),
+        _ if request.json()["options"]["cas"] == 3 => response("200 OK", &write_body(4)),
+        _ => cas_mismatch(),
+    });
+    let client = server_client(addr, &[]);
+
+    let (value, etag) = client.read_with_etag::<Value>("app/db").await.unwrap();
+    assert_eq!(
+        (value, etag.as_str()),
+        (serde_json::json!({ "v": 1 }), "\"2\"")
+    );
+    match client
+        .write_if_etag_matches("app/db", &serde_json::json!({ "v": 2 }), &etag)
+        .await
+    {
+        Err(VaultError::CasMismatch { namespace, path }) => {
+            assert_eq!((namespace.as_str(), path.as_str()), ("secret", "app/db"))
+        }
+        other => panic!("expected cas mismatch, got {:?}", other),
+    }
+    assert!(matches!(
+        client
+            .write_if_etag_matches("app/db", &serde_json::json!({ "v": 2 }), "W/\"x\"")
+            .await,
+        Err(VaultError::CasMismatch { .. })
+    ));
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[1].json(),
+        serde_json::json!({ "data": { "v": 2 }, "options": { "cas": 2 } })
+    );
+}
+
+/// Answers the requests of delete_prefix for the keys `app/a`, `app/b` and `app/sub/c`,
+/// failing the delete of `app/b`
+fn delete_prefix_server(request: &str) -> String {
+    let mut parts = request.split(' ');
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default().split('?').next().unwrap();
+    match (method, path) {
+        (_, "/v1/secret/metadata/app") if method != "GET" => {
+            response("200 OK", r#"{"data":{"keys":["a","b","sub/"]}}"#)
+        }
+        (_, "/v1/secret/metadata/app/sub") if method != "GET" => {
+            response("200 OK", r#"{"data":{"keys":["c"]}}"#)
+        }
+        ("DELETE", "/v1/secret/data/app/b") => {
+            response("500 Internal Server Error", r#"{"errors":["boom"]}"#)
+        }
+        ("DELETE", _) => "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n".to_string(),
+        _ => response("404 Not Found", r#"{"errors":[]}"#),
+    }
+}
+
+#[tokio::test]
+async fn delete_prefix_dry_run() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, delete_prefix_server);
+    let client = server_client(addr, &[]);
+
+    let keys = client
+        .delete_prefix("app", DeleteMode::Soft, true)
+        .await
+        .unwrap();
+    assert_eq!(keys, vec!["app/a", "app/b", "app/sub/c"]);
+    assert!(!requests
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|r| r.starts_with("DELETE")));
+}
+
+#[tokio::test]
+async fn delete_prefix_partial_failure() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, delete_prefix_server);
+    let client = server_client(addr, &[]);
+
+    match client.delete_prefix("app", DeleteMode::Soft, false).await {
+        Err(VaultError::PartialDelete { deleted, errors }) => {
+            assert_eq!(deleted, 2);
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].0, "app/b");
+        }
+        other => panic!("expected partial delete, got {:?}", other),
+    }
+    let mut deletes = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|r| r.starts_with("DELETE"))
+        .cloned()
+        .collect::<Vec<_>>();
+    deletes.sort();
+    assert_eq!(
+        deletes,
+        vec![
+            "DELETE /v1/secret/data/app/a HTTP/1.1",
+            "DELETE /v1/secret/data/app/b HTTP/1.1",
+            "DELETE /v1/secret/data/app/sub/c HTTP/1.1",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn wait_until_unsealed_ignores_status_overrides() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    serve(listener, |_| {
+        response("503 Service Unavailable", r#"{"sealed":true}"#)
+    });
+    let client = server_client(addr, &[("success_status_codes", "503")]);
+    assert!(matches!(
+        client.wait_until_unsealed(Duration::from_millis(500)).await,
+        Err(VaultError::Timeout { .. })
+    ));
+}
+
+#[tokio::test]
+async fn wait_until_unsealed_bypasses_breaker() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    serve(listener, |request| {
+        match request.contains("/v1/sys/health") {
+            true => response("200 OK", r#"{"sealed":false}"#),
+            false => response("500 Internal Server Error", r#"{"errors":["boom"]}"#),
+        }
+    });
+    let client = server_client(addr, &[("circuit_breaker_threshold", "1")]);
+    assert!(client.read_secret::<Value>("app/db").await.is_err());
+    assert!(matches!(
+        client.read_secret::<Value>("app/db").await,
+        Err(VaultError::CircuitOpen { .. })
+    ));
+    client
+        .wait_until_unsealed(Duration::from_secs(2))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn read_after_write_sends_index() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve_with(listener, |request| match request.method() {
+        "POST" => {
+            let body = write_body(1);
+            format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nx-vault-index: index-1\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => response("200 OK", &read_body(serde_json::json!({ "v": 1 }), 1)),
+    });
+    let client = server_client(addr, &[("require_consistency", "true")]);
+
+    client.read_secret::<Value>("app/db").await.unwrap();
+    client
+        .write_secret("app/db", &serde_json::json!({ "v": 1 }))
+        .await
+        .unwrap();
+    client.read_secret::<Value>("app/db").await.unwrap();
+    let requests = requests.lock().unwrap();
+    // nothing has been written before the first read
+    assert!(!requests[0].headers.contains_key("x-vault-index"));
+    assert_eq!(requests[2].headers["x-vault-index"], "index-1");
+    assert_eq!(
+        requests[2].headers["x-vault-inconsistent"],
+        "forward-active-node"
+    );
+}
+
+#[tokio::test]
+async fn response_too_large() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    serve(listener, |request| {
+        let body = read_body(serde_json::json!({ "v": "x".repeat(200) }), 1);
+        match request.contains("/chunked") {
+            // without a content length, the size is only known once the body has been read
+            true => format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                body.len(),
+                body
+            ),
+            false => response("200 OK", &body),
+        }
+    });
+    let client = server_client(addr, &[("max_response_bytes", "100")]);
+
+    for path in ["app/sized", "app/chunked"] {
+        match client.read_secret::<Value>(path).await {
+            Err(VaultError::ResponseTooLarge { limit, actual }) => {
+                assert_eq!(limit, 100);
+                assert!(actual > 100, "{}", actual);
+            }
+            other => panic!("expected response too large, got {:?}", other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn identity_token_role_encoded() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |_| {
+        response(
+            "200 OK",
+            r#"{"data":{"token":"jwt","ttl":60,"client_id":"web"}}"#,
+        )
+    });
+    let client = server_client(addr, &[]);
+
+    let token = client.issue_identity_token("web/app 1").await.unwrap();
+    assert_eq!(token.token, "jwt");
+    assert_eq!(
+        requests.lock().unwrap()[0],
+        "GET /v1/identity/oidc/token/web%2Fapp%201 HTTP/1.1"
+    );
+}
+
+#[tokio::test]
+async fn stamp_provenance_requires_metadata_patch() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |request| {
+        match request.contains("/v1/sys/seal-status") {
+            true => response("200 OK", r#"{"version":"1.8.4"}"#),
+            false => response("200 OK", &write_body(2)),
+        }
+    });
+    let client = server_client(addr, &[("stamp_provenance", "true")]);
+
+    match client
+        .write_secret("app/db", &serde_json::json!({ "user": "admin" }))
+        .await
+    {
+        Err(VaultError::UnsupportedByServer { min_version, .. }) => {
+            assert_eq!(min_version, semver::Version::new(1, 9, 0));
+        }
+        other => panic!("expected unsupported by server, got {:?}", other),
+    }
+    // the version is checked before anything is written
+    assert_eq!(
+        *requests.lock().unwrap(),
+        ["GET /v1/sys/seal-status HTTP/1.1"]
+    );
+}
+
+#[tokio::test]
+async fn delete_if_version() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve_with(listener, |request| match request.method() {
+        "GET" => response(
+            "200 OK",
+            r#"{"data":{"cas_required":false,"created_time":"2020-01-01T00:00:00Z","current_version":3,"delete_version_after":"0s","max_versions":0,"oldest_version":0,"updated_time":"2020-01-01T00:00:00Z","custom_metadata":null,"versions":{}}}"#,
+        ),
+        _ => "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n".to_string(),
+    });
+    let client = server_client(addr, &[]);
+
+    assert!(matches!(
+        client
+            .delete_if_version("app/db", 2, DeleteMode::Soft)
+            .await,
+        Err(VaultError::CasMismatch { .. })
+    ));
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    client
+        .delete_if_version("app/db", 3, DeleteMode::Soft)
+        .await
+        .unwrap();
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[2].line, "POST /v1/secret/delete/app/db HTTP/1.1");
+    assert_eq!(requests[2].json(), serde_json::json!({ "versions": [3] }));
+}
+
+#[tokio::test]
+async fn routed_delete_and_list() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |request| {
+        if request.starts_with("GET /v1/sys/internal/ui/mounts/") {
+            return response("200 OK", r#"{"data":{"options":{"version":"2"}}}"#);
+        }
+        match request.starts_with("LIST") {
+            true => response("200 OK", r#"{"data":{"keys":["app"]}}"#),
+            false => "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n".to_string(),
+        }
+    });
+    let client = server_client(addr, &[("mount_routes", "prod=kv-prod")]);
+
+    client.delete_latest::<String>("prod/app").await.unwrap();
+    assert_eq!(client.list_secrets("prod/").await.unwrap(), ["app"]);
+    assert_eq!(client.list_secrets("").await.unwrap(), ["app"]);
+    // the routed mount is detected once, the configured mount is pinned to kv v2
+    assert_eq!(
+        *requests.lock().unwrap(),
+        [
+            "GET /v1/sys/internal/ui/mounts/kv-prod HTTP/1.1",
+            "DELETE /v1/kv-prod/data/app HTTP/1.1",
+            "LIST /v1/kv-prod/metadata/ HTTP/1.1",
+            "LIST /v1/secret/metadata/ HTTP/1.1",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn write_secret_idempotent_skips_retry() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let custom_metadata = Mutex::new(None::<Value>);
+    let requests = serve_with(listener, move |request| {
+        let mut custom_metadata = custom_metadata.lock().unwrap();
+        if request.line.contains("/v1/sys/seal-status") {
+            return response("200 OK", r#"{"version":"1.9.0"}"#);
+        }
+        match (request.method(), request.line.contains("/metadata/")) {
+            ("GET", _) => match &*custom_metadata {
+                Some(custom) => response(
+                    "200 OK",
+                    &serde_json::json!({
+                        "data": {
+                            "current_version": 1,
+                            "custom_metadata": custom,
+                            "versions": {
+                                "1": {
+                                    "created_time": "2022-01-01T00:00:00Z",
+                                    "deletion_time": "",
+                                    "destroyed": false,
+                                },
+                            },
+                        },
+                    })
+                    .to_string(),
+                ),
+                None => response("404 Not Found", r#"{"errors":[]}"#),
+            },
+            (_, true) => {
+                *custom_metadata = Some(request.json()["custom_metadata"].clone());
+                "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n".to_string()
+            }
+            (_, false) => response("200 OK", &write_body(1)),
+        }
+    });
+    let client = server_client(addr, &[]);
+    let data = serde_json::json!({ "password": "x" });
+
+    let first = client
+        .write_secret_idempotent("app/db", &data, "req-1")
+        .await
+        .unwrap();
+    assert_eq!(first.version, 1);
+    let retry = client
+        .write_secret_idempotent("app/db", &data, "req-1")
+        .await
+        .unwrap();
+    assert_eq!(retry.version, 1);
+
+    let requests = requests.lock().unwrap();
+    let lines = requests.iter().map(|r| r.line.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "GET /v1/sys/seal-status HTTP/1.1",
+            "GET /v1/secret/metadata/app/db HTTP/1.1",
+            "POST /v1/secret/data/app/db HTTP/1.1",
+            "PATCH /v1/secret/metadata/app/db HTTP/1.1",
+            "GET /v1/secret/metadata/app/db HTTP/1.1",
+        ]
+    );
+    assert_eq!(requests[2].json()["options"]["cas"], 0);
+    assert_eq!(
+        requests[3].json(),
+        serde_json::json!({ "custom_metadata": { "idempotency_key": "req-1" } })
+    );
+}
+
+/// Serves a secret whose metadata records the idempotency key `req-1`, on vault of the version
+fn idempotent_server(listener: TcpListener, version: &'static str) -> Arc<Mutex<Vec<Request>>> {
+    serve_with(listener, move |request| {
+        match request.line.contains("/v1/sys/seal-status") {
+            true => response("200 OK", &format!(r#"{{"version":"{}"}}"#, version)),
+            false => response(
+                "200 OK",
+                r#"{"data":{"current_version":3,"custom_metadata":{"idempotency_key":"req-1","owner":"billing"},"versions":{"3":{"created_time":"2022-01-01T00:00:00Z","deletion_time":"","destroyed":false}}}}"#,
+            ),
+        }
+    })
+}
+
+#[tokio::test]
+async fn write_secret_idempotent_key_present() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = idempotent_server(listener, "1.13.1");
+    let client = server_client(addr, &[]);
+
+    let written = client
+        .write_secret_idempotent("app/db", &serde_json::json!({ "password": "x" }), "req-1")
+        .await
+        .unwrap();
+    assert_eq!(written.version, 3);
+    assert_eq!(written.created_time, "2022-01-01T00:00:00Z");
+    assert_eq!(written.custom_metadata.unwrap()["owner"], "billing");
+    // nothing is written
+    assert!(requests.lock().unwrap().iter().all(|r| r.method() == "GET"));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = idempotent_server(listener, "1.8.4");
+    let client = server_client(addr, &[]);
+    assert!(matches!(
+        client
+            .write_secret_idempotent("app/db", &serde_json::json!({ "password": "x" }), "req-2")
+            .await,
+        Err(VaultError::UnsupportedByServer { .. })
+    ));
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn stamp_provenance_patches_metadata() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve_with(listener, |request| {
+        match request.line.contains("/v1/sys/seal-status") {
+            true => response("200 OK", r#"{"version":"1.9.0"}"#),
+            false => response("200 OK", &write_body(4)),
+        }
+    });
+    let client = server_client(
+        addr,
+        &[("stamp_provenance", "true"), ("client_id", "billing")],
+    );
+
+    let written = client
+        .write_secret("app/db", &serde_json::json!({ "user": "admin" }))
+        .await
+        .unwrap();
+    assert_eq!(written.version, 4);
+
+    let requests = requests.lock().unwrap();
+    let patch = requests.last().unwrap();
+    assert_eq!(patch.line, "PATCH /v1/secret/metadata/app/db HTTP/1.1");
+    assert_eq!(
+        patch.headers["content-type"],
+        "application/merge-patch+json"
+    );
+    let custom = &patch.json()["custom_metadata"];
+    assert_eq!(custom[WRITTEN_AT_FIELD], "2022-01-01T00:00:00Z");
+    assert_eq!(custom[CLIENT_ID_FIELD], "billing");
+    assert!(custom[WRITTEN_BY_FIELD]
+        .as_str()
+        .unwrap()
+        .starts_with(env!("CARGO_PKG_NAME")));
+}
+
+#[tokio::test]
+async fn rotate_secret_retries_conflict() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    // the first write loses to a concurrent write of version 2
+    let version = Mutex::new(1);
+    let requests = serve_with(listener, move |request| {
+        let mut version = version.lock().unwrap();
+        match (request.method(), *version) {
+            ("GET", v) => response("200 OK", &read_body(serde_json::json!({ "n": v * 10 }), v)),
+            (_, 1) => {
+                *version = 2;
+                cas_mismatch()
+            }
+            (_, v) => response("200 OK", &write_body(v + 1)),
+        }
+    });
+    let client = server_client(addr, &[]);
+
+    let rotated: Value = client
+        .rotate_secret("app/db", |current: Option<Value>| {
+            serde_json::json!({ "n": current.unwrap()["n"].as_u64().unwrap() + 1 })
+        })
+        .await
+        .unwrap();
+    assert_eq!(rotated, serde_json::json!({ "n": 21 }));
+
+    let requests = requests.lock().unwrap();
+    let writes = requests
+        .iter()
+        .filter(|r| r.method() == "POST")
+        .map(|r| r.json())
+        .collect::<Vec<_>>();
+    assert_eq!(writes.len(), 2);
+    assert_eq!(writes[0]["options"]["cas"], 1);
+    assert_eq!(writes[1]["options"]["cas"], 2);
+    assert_eq!(writes[1]["data"], serde_json::json!({ "n": 21 }));
+}
+
+#[tokio::test]
+async fn sample_secret_ages_until_cancelled() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |_| {
+        response(
+            "200 OK",
+            r#"{"data":{"cas_required":false,"created_time":"2020-01-01T00:00:00Z","current_version":1,"delete_version_after":"0s","max_versions":0,"oldest_version":0,"updated_time":"2020-01-01T00:00:00Z","custom_metadata":null,"versions":{}}}"#,
+        )
+    });
+    let client = server_client(addr, &[]);
+    let token = CancellationToken::new();
+
+    let sampler = tokio::spawn({
+        let token = token.clone();
+        async move {
+            client
+                .sample_secret_ages(&["app/db".to_string()], Duration::from_millis(50), token)
+                .await
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(120)).await;
+    token.cancel();
+    sampler.await.unwrap();
+
+    let sampled = requests.lock().unwrap().len();
+    assert!(sampled >= 2, "{}", sampled);
+    assert!(requests
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|r| r == "GET /v1/secret/metadata/app/db HTTP/1.1"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(requests.lock().unwrap().len(), sampled);
+}
+
+#[tokio::test]
+async fn delete_prefix_metadata_denied() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    serve(listener, |request| {
+        match request.starts_with("GET /v1/secret/metadata/app ") {
+            true => response("403 Forbidden", r#"{"errors":["permission denied"]}"#),
+            false => delete_prefix_server(request),
+        }
+    });
+    let client = server_client(addr, &[]);
+
+    // a denied read of the prefix key fails the listing instead of hiding the key
+    assert!(matches!(
+        client.delete_prefix("app", DeleteMode::Soft, true).await,
+        Err(VaultError::Client { .. })
+    ));
+}
+
+#[tokio::test]
+async fn wait_until_unsealed_unresponsive() {
+    // the listener accepts connections but never responds
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = server_client(listener.local_addr().unwrap(), &[]);
+
+    let start = std::time::Instant::now();
+    assert!(matches!(
+        client.wait_until_unsealed(Duration::from_millis(200)).await,
+        Err(VaultError::Timeout { .. })
+    ));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn propagates_trace_context() {
+    use opentelemetry::trace::{TraceContextExt, TracerProvider};
+    use tracing::Instrument;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve_with(listener, |_| {
+        response("200 OK", &read_body(serde_json::json!({ "a": 1 }), 1))
+    });
+    let client = server_client(addr, &[("propagate_trace_context", "true")]);
+
+    // the tracer only holds a weak reference to its provider
+    let provider = opentelemetry::sdk::trace::TracerProvider::builder().build();
+    let tracer = provider.tracer("client_test");
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_subscriber::filter::LevelFilter::INFO)
+        .with(tracing_opentelemetry::layer().with_tracer(tracer));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let span = tracing::info_span!("caller");
+    let trace_id = span.context().span().span_context().trace_id();
+    client
+        .read_secret::<Value>("app/db")
+        .instrument(span)
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    let traceparent = &requests[0].headers["traceparent"];
+    let parts = traceparent.split('-').collect::<Vec<_>>();
+    assert_eq!(parts.len(), 4, "{}", traceparent);
+    assert_eq!(parts[0], "00");
+    assert_eq!(parts[1], trace_id.to_string());
+    assert_ne!(parts[1], "0".repeat(32));
+    assert_ne!(parts[2], "0".repeat(16));
+}
+
+#[tokio::test]
+async fn stamp_provenance_on_cas_writes() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve_with(listener, |request| match request.method() {
+        "GET" if request.line.contains("/v1/sys/seal-status") => {
+            response("200 OK", r#"{"version":"1.9.0"}"#)
+        }
+        "GET" => response("200 OK", &read_body(serde_json::json!({ "v": 1 }), 2)),
+        _ => response("200 OK", &write_body(3)),
+    });
+    let client = server_client(addr, &[("stamp_provenance", "true")]);
+
+    client
+        .write_if_etag_matches("app/db", &serde_json::json!({ "v": 2 }), "\"2\"")
+        .await
+        .unwrap();
+    client
+        .rotate_secret("app/db", |_: Option<Value>| serde_json::json!({ "v": 3 }))
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    let lines = requests.iter().map(|r| r.line.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "GET /v1/sys/seal-status HTTP/1.1",
+            "POST /v1/secret/data/app/db HTTP/1.1",
+            "PATCH /v1/secret/metadata/app/db HTTP/1.1",
+            "GET /v1/secret/data/app/db HTTP/1.1",
+            "POST /v1/secret/data/app/db HTTP/1.1",
+            "PATCH /v1/secret/metadata/app/db HTTP/1.1",
+        ]
+    );
+    assert_eq!(
+        requests[2].json()["custom_metadata"][WRITTEN_AT_FIELD],
+        "2022-01-01T00:00:00Z"
+    );
+}
+
+#[tokio::test]
+async fn detects_kv_version_on_first_use() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve(listener, |request| {
+        match request.split(' ').take(2).collect::<Vec<_>>()[..] {
+            ["GET", "/v1/sys/internal/ui/mounts/secret"] => {
+                response("200 OK", r#"{"data":{"options":{"version":"2"}}}"#)
+            }
+            ["GET", "/v1/sys/internal/ui/mounts/kv-old"] => {
+                response("200 OK", r#"{"data":{"options":{"version":"1"}}}"#)
+            }
+            _ => response("200 OK", &read_body(serde_json::json!({ "k": "v" }), 1)),
+        }
+    });
+    let client = server_client(
+        addr,
+        &[("kv_version", "auto"), ("mount_routes", "old=kv-old")],
+    );
+
+    client.read_secret::<Value>("app/db").await.unwrap();
+    client.read_secret::<Value>("app/db").await.unwrap();
+    client.read_secret::<Value>("old/app").await.unwrap();
+    assert_eq!(
+        *requests.lock().unwrap(),
+        [
+            "GET /v1/sys/internal/ui/mounts/secret HTTP/1.1",
+            "GET /v1/secret/data/app/db HTTP/1.1",
+            "GET /v1/secret/data/app/db HTTP/1.1",
+            "GET /v1/sys/internal/ui/mounts/kv-old HTTP/1.1",
+            "GET /v1/kv-old/app HTTP/1.1",
+        ]
+    );
+    assert_eq!(
+        client.kv_versions(),
+        HashMap::from([
+            ("secret".to_string(), KvVersion::V2),
+            ("kv-old".to_string(), KvVersion::V1),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn kv1_mount_operations() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = serve_with(listener, |request| {
+        match (request.method(), request.line.split(' ').nth(1).unwrap()) {
+            ("GET", "/v1/sys/internal/ui/mounts/kv-old") => {
+                response("200 OK", r#"{"data":{"options":{"version":"1"}}}"#)
+            }
+            ("GET", _) => response("200 OK", r#"{"data":{"user":"admin"}}"#),
+            ("LIST", _) => response("200 OK", r#"{"data":{"keys":["db"]}}"#),
+            _ => "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n".to_string(),
+        }
+    });
+    let sink = Arc::new(RecordingSink::default());
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("addr".to_string(), format!("http://{}", addr)),
+        ("kv_version".to_string(), "2".to_string()),
+        ("mount_routes".to_string(), "old=kv-old".to_string()),
+    ]);
+    let mut config = Config::from_values(&values).unwrap();
+    config.audit_sink = Some(sink.clone());
+    let client = Client::new(config).unwrap();
+
+    let data = serde_json::json!({ "user": "admin" });
+    assert_eq!(
+        client.read_secret::<Value>("old/app/db").await.unwrap(),
+        data
+    );
+    client.write_secret("old/app/db", &data).await.unwrap();
+    assert_eq!(client.list_secrets("old/app").await.unwrap(), ["db"]);
+    client.delete_latest::<Value>("old/app/db").await.unwrap();
+    // kv v1 has no versions for check-and-set
+    assert!(matches!(
+        client.rotate_secret::<Value, _>("old/app/db", |_| data.clone()).await,
+        Err(VaultError::UnsupportedKvVersion { mount }) if mount == "kv-old"
+    ));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests.iter().map(|r| r.line.as_str()).collect::<Vec<_>>(),
+        [
+            "GET /v1/sys/internal/ui/mounts/kv-old HTTP/1.1",
+            "GET /v1/kv-old/app/db HTTP/1.1",
+            "POST /v1/kv-old/app/db HTTP/1.1",
+            "LIST /v1/kv-old/app HTTP/1.1",
+            "DELETE /v1/kv-old/app/db HTTP/1.1",
+            "GET /v1/kv-old/app/db HTTP/1.1",
+        ]
+    );
+    // the kv v1 body is the secret itself, without the kv v2 data wrapper
+    assert_eq!(requests[2].json(), data);
+
+    let events = sink.0.lock().unwrap();
+    assert_eq!(
+        events
+            .iter()
+            .map(|e| (e.operation, e.mount.as_str(), e.path.as_str()))
+            .collect::<Vec<_>>(),
+        [
+            (AuditOperation::Read, "kv-old", "app/db"),
+            (AuditOperation::Write, "kv-old", "app/db"),
+            (AuditOperation::List, "kv-old", "app"),
+            (AuditOperation::Delete, "kv-old", "app/db"),
+            (AuditOperation::Read, "kv-old", "app/db"),
+        ]
+    );
+}
@@ -0,0 +1,24 @@
+//! Tests compression of stored secrets
+//!
+use kv_vault_lib::compression::{compress, decompress, Codec, COMPRESSION_MARKER};
+use serde_json::json;
+
+#[test]
+fn compression_round_trip() {
+    let value = json!({ "cert": "-----BEGIN CERTIFICATE-----".repeat(100), "n": 1 });
+    for codec in [Codec::Gzip, Codec::Zstd] {
+        let compressed = compress(&value, codec).unwrap();
+        assert!(compressed.get(COMPRESSION_MARKER).is_some());
+        assert!(compressed.to_string().len() < value.to_string().len());
+        assert_eq!(decompress(compressed).unwrap(), value);
+    }
+}
+
+#[test]
+fn unknown_marker_passes_through() {
+    let value = json!({ COMPRESSION_MARKER: "lz4", "__payload": "abc" });
+    assert_eq!(decompress(value.clone()).unwrap(), value);
+    let value = json!({ "plain": true });
+    assert_eq!(decompress(value.clone()).unwrap(), value);
+    assert!(decompress(json!({ COMPRESSION_MARKER: "gzip", "__payload": "!!" })).is_err());
+}
@@ -0,0 +1,111 @@
+//! Tests config parsing
+//!
+use std::{collections::HashMap, time::Duration};
+
+use kv_vault_lib::{
+    client::KvVersion,
+    config::{Config, TlsVersion},
+};
+
+fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    let mut values: HashMap<String, String> = pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    values.insert("token".to_string(), "test-token".to_string());
+    values
+}
+
+#[test]
+fn interpolate_env_vars() {
+    std::env::set_var("KV_VAULT_TEST_MOUNT", "kv-test");
+    std::env::remove_var("KV_VAULT_TEST_UNSET");
+
+    let config = Config::from_values(&values(&[("mount", "${KV_VAULT_TEST_MOUNT}")])).unwrap();
+    assert_eq!(config.mount, "kv-test");
+
+    let config =
+        Config::from_values(&values(&[("mount", "app-${KV_VAULT_TEST_MOUNT}/x")])).unwrap();
+    assert_eq!(config.mount, "app-kv-test/x");
+
+    let config =
+        Config::from_values(&values(&[("mount", "${KV_VAULT_TEST_UNSET:-fallback}")])).unwrap();
+    assert_eq!(config.mount, "fallback");
+
+    let config = Config::from_values(&values(&[("mount", "a$$b$c")])).unwrap();
+    assert_eq!(config.mount, "a$b$c");
+
+    assert!(Config::from_values(&values(&[("mount", "${KV_VAULT_TEST_UNSET}")])).is_err());
+    assert!(Config::from_values(&values(&[("mount", "${KV_VAULT_TEST_MOUNT")])).is_err());
+
+    let err = Config::from_values(&values(&[("client_id", "s.secret${x")])).unwrap_err();
+    assert!(!err.to_string().contains("s.secret"), "{}", err);
+    assert!(err.to_string().contains("offset 8"), "{}", err);
+}
+
+#[test]
+fn min_tls_version() {
+    let config = Config::from_values(&values(&[])).unwrap();
+    assert_eq!(config.min_tls_version, Some(TlsVersion::Tls1_2));
+
+    let config = Config::from_values(&values(&[("min_tls_version", "1.3")])).unwrap();
+    assert_eq!(config.min_tls_version, Some(TlsVersion::Tls1_3));
+
+    let config = Config::from_values(&values(&[("min_tls_version", "TLS1.2")])).unwrap();
+    assert_eq!(config.min_tls_version, Some(TlsVersion::Tls1_2));
+
+    assert!(Config::from_values(&values(&[("min_tls_version", "1.1")])).is_err());
+}
+
+#[test]
+fn empty_mount_uses_default() {
+    let config = Config::from_values(&values(&[("mount", "")])).unwrap();
+    assert_eq!(config.mount, "secret");
+    let config = Config::from_values(&values(&[("mount", " / ")])).unwrap();
+    assert_eq!(config.mount, "secret");
+    let config = Config::from_values(&values(&[("mount", "/kv/")])).unwrap();
+    assert_eq!(config.mount, "kv");
+}
+
+#[test]
+fn success_status_codes() {
+    let config = Config::from_values(&values(&[])).unwrap();
+    assert_eq!(config.success_status_codes, None);
+
+    let config = Config::from_values(&values(&[("success_status_codes", "203, 299,")])).unwrap();
+    assert_eq!(config.success_status_codes, Some(vec![203, 299]));
+
+    assert!(Config::from_values(&values(&[("success_status_codes", "600")])).is_err());
+    assert!(Config::from_values(&values(&[("success_status_codes", "ok")])).is_err());
+}
+
+#[test]
+fn secret_age_sampler() {
+    let config = Config::from_values(&values(&[])).unwrap();
+    assert!(config.secret_age_paths.is_empty());
+    assert_eq!(config.secret_age_interval, Duration::from_secs(300));
+
+    let config = Config::from_values(&values(&[
+        ("secret_age_paths", "app/db, app/api,"),
+        ("secret_age_interval", "60"),
+    ]))
+    .unwrap();
+    assert_eq!(config.secret_age_paths, ["app/db", "app/api"]);
+    assert_eq!(config.secret_age_interval, Duration::from_secs(60));
+
+    assert!(Config::from_values(&values(&[("secret_age_interval", "0")])).is_err());
+}
+
+#[test]
+fn kv_version() {
+    let config = Config::from_values(&values(&[])).unwrap();
+    assert_eq!(config.kv_version, None);
+    let config = Config::from_values(&values(&[("kv_version", "auto")])).unwrap();
+    assert_eq!(config.kv_version, None);
+    let config = Config::from_values(&values(&[("kv_version", "1")])).unwrap();
+    assert_eq!(config.kv_version, Some(KvVersion::V1));
+    let config = Config::from_values(&values(&[("kv_version", "v2")])).unwrap();
+    assert_eq!(config.kv_version, Some(KvVersion::V2));
+
+    assert!(Config::from_values(&values(&[("kv_version", "3")])).is_err());
+}
@@ -0,0 +1,42 @@
+//! Tests field-level diffs of secrets
+//!
+use kv_vault_lib::diff::{diff_fields, ChangeKind};
+use serde_json::json;
+
+#[test]
+fn diff_redacted() {
+    let from = json!({ "user": "app", "password": "old", "port": 5432 });
+    let to = json!({ "user": "app", "password": "new", "host": "db" });
+    let changes = diff_fields(&from, &to, false);
+
+    let fields = changes
+        .iter()
+        .map(|c| (c.field.as_str(), c.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        vec![
+            ("host", ChangeKind::Added),
+            ("password", ChangeKind::Changed),
+            ("port", ChangeKind::Removed),
+        ]
+    );
+    let password = &changes[1];
+    let (old, new) = (
+        password.from.as_ref().unwrap(),
+        password.to.as_ref().unwrap(),
+    );
+    assert!(old.starts_with("sha256:") && !old.contains("old"));
+    assert_ne!(old, new);
+    assert_eq!(changes[0].from, None);
+    assert_eq!(changes[2].to, None);
+}
+
+#[test]
+fn diff_with_values() {
+    let changes = diff_fields(&json!({ "a": 1 }), &json!({ "a": "1" }), true);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].from.as_deref(), Some("1"));
+    assert_eq!(changes[0].to.as_deref(), Some("\"1\""));
+    assert!(diff_fields(&json!({ "a": 1 }), &json!({ "a": 1 }), true).is_empty());
+}
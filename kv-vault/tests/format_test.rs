@@ -0,0 +1,40 @@
+//! Tests text formats of secrets
+//!
+use kv_vault_lib::format::SecretFormat;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Database {
+    host: String,
+    port: u16,
+    replicas: Vec<String>,
+}
+
+#[test]
+fn format_round_trip() {
+    let db = Database {
+        host: "db.internal".to_string(),
+        port: 5432,
+        replicas: vec!["r1".to_string(), "r2".to_string()],
+    };
+    for format in [SecretFormat::Json, SecretFormat::Toml, SecretFormat::Yaml] {
+        let text = format.render(&db).unwrap();
+        assert_eq!(format.parse::<Database>(&text).unwrap(), db, "{}", format);
+    }
+}
+
+#[test]
+fn format_parse() {
+    let db: Database = SecretFormat::Toml
+        .parse("host = \"h\"\nport = 1\nreplicas = []\n")
+        .unwrap();
+    assert_eq!(db.port, 1);
+    let db: Database = SecretFormat::Yaml
+        .parse("host: h\nport: 2\nreplicas: [a]\n")
+        .unwrap();
+    assert_eq!(db.replicas, vec!["a"]);
+    assert!(SecretFormat::Toml.parse::<Database>("host: h").is_err());
+
+    assert_eq!("YML".parse::<SecretFormat>(), Ok(SecretFormat::Yaml));
+    assert!("ini".parse::<SecretFormat>().is_err());
+}
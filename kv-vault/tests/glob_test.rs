@@ -0,0 +1,19 @@
+//! Tests glob matching of listed keys
+//!
+use kv_vault_lib::client::glob_match;
+
+#[test]
+fn glob_wildcards() {
+    assert!(glob_match("db-*", "db-primary"));
+    assert!(glob_match("db-*", "db-"));
+    assert!(!glob_match("db-*", "cache-db"));
+    assert!(glob_match("*-db", "cache-db"));
+    assert!(glob_match("a*b*c", "axxbyyc"));
+    assert!(!glob_match("a*b*c", "axxbyy"));
+    assert!(glob_match("key?", "key1"));
+    assert!(!glob_match("key?", "key"));
+    assert!(!glob_match("key?", "key12"));
+    assert!(glob_match("*", ""));
+    assert!(glob_match("exact", "exact"));
+    assert!(!glob_match("exact", "exactly"));
+}
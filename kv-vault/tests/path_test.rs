@@ -0,0 +1,83 @@
+//! Tests secret path normalization
+//!
+use kv_vault_lib::{
+    error::VaultError,
+    path::{decode, encode, encode_segment, normalize, normalize_dir},
+    store::{MockSecretStore, SecretStore},
+};
+use serde_json::json;
+
+#[test]
+fn normalize_paths() {
+    assert_eq!(normalize("app/db").unwrap(), "app/db");
+    assert_eq!(normalize("/app/db").unwrap(), "app/db");
+    assert_eq!(normalize("app//db/").unwrap(), "app/db");
+    assert_eq!(normalize("//app///db//").unwrap(), "app/db");
+    assert_eq!(normalize("a.b/..c").unwrap(), "a.b/..c");
+
+    for path in ["", "/", "//"] {
+        assert!(matches!(
+            normalize(path),
+            Err(VaultError::InvalidPath { .. })
+        ));
+    }
+    for path in ["../app", "app/../db", "app/./db", "app/.."] {
+        assert!(matches!(
+            normalize(path),
+            Err(VaultError::InvalidPath { .. })
+        ));
+    }
+}
+
+#[test]
+fn normalize_dirs() {
+    assert_eq!(normalize_dir("").unwrap(), "");
+    assert_eq!(normalize_dir("/").unwrap(), "");
+    assert_eq!(normalize_dir("/app//").unwrap(), "app");
+    assert!(normalize_dir("app/..").is_err());
+}
+
+#[test]
+fn encode_segments() {
+    assert_eq!(encode("certs/my.example.com"), "certs/my.example.com");
+    assert_eq!(encode("app/my key"), "app/my%20key");
+    assert_eq!(encode("app/50%off"), "app/50%25off");
+    assert_eq!(encode("app/a%2Fb"), "app/a%252Fb");
+    assert_eq!(encode("q?x=1#frag"), "q%3Fx%3D1%23frag");
+    assert_eq!(encode("caf\u{e9}"), "caf%C3%A9");
+    assert_eq!(encode("a-b_c~d"), "a-b_c~d");
+}
+
+#[test]
+fn encode_single_segment() {
+    assert_eq!(encode_segment("my-role"), "my-role");
+    assert_eq!(encode_segment("../sys/seal"), "..%2Fsys%2Fseal");
+    assert_eq!(encode_segment("a b?"), "a%20b%3F");
+}
+
+#[test]
+fn decode_round_trip() {
+    for path in ["app/db", "my key/p%q?r#s", "ключ/значение"] {
+        assert_eq!(decode(&encode(path)), path);
+    }
+    assert_eq!(decode("a%2"), "a%2");
+    assert_eq!(decode("a%zzb"), "a%zzb");
+}
+
+#[tokio::test]
+async fn equivalent_paths_same_key() {
+    let store = MockSecretStore::new("secret");
+    store
+        .write_secret("/app//db", &json!({ "v": 1 }))
+        .await
+        .unwrap();
+    assert_eq!(
+        store.read_secret("app/db").await.unwrap(),
+        json!({ "v": 1 })
+    );
+    assert_eq!(store.list_secrets("//app/").await.unwrap(), vec!["db"]);
+    assert!(matches!(
+        store.read_secret("app/../app/db").await,
+        Err(VaultError::InvalidPath { .. })
+    ));
+}
@@ -0,0 +1,30 @@
+//! Tests sharing of connections between clients
+//!
+use std::collections::HashMap;
+
+use kv_vault_lib::{config::Config, pool::ClientPool};
+
+fn config(mount: &str, token: &str) -> Config {
+    let values = HashMap::from([
+        ("mount".to_string(), mount.to_string()),
+        ("token".to_string(), token.to_string()),
+    ]);
+    Config::from_values(&values).unwrap()
+}
+
+#[test]
+fn pool_shares_connections() {
+    let pool = ClientPool::new();
+    let secret = pool.client(config("secret", "t1")).unwrap();
+    let other = pool.client(config("other", "t1")).unwrap();
+    assert_eq!(pool.connection_count(), 1);
+
+    let different_token = pool.client(config("secret", "t2")).unwrap();
+    assert_eq!(pool.connection_count(), 2);
+
+    drop(secret);
+    assert_eq!(pool.connection_count(), 2);
+    drop(other);
+    drop(different_token);
+    assert_eq!(pool.connection_count(), 0);
+}
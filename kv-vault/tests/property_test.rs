@@ -0,0 +1,97 @@
+//! Property tests of path handling and secret round-trips
+//!
+use kv_vault_lib::{
+    compression::{compress, decompress, Codec},
+    path::{decode, encode, normalize},
+    store::{MockSecretStore, SecretStore},
+};
+use proptest::prelude::*;
+use serde_json::{Map, Value};
+
+/// Key names, including unicode, spaces, dots, and url metacharacters.
+/// `.` and `..` are excluded, since they are rejected.
+fn key_name() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-zA-Z0-9_-]{1,16}",
+        "[ %?#&=+.~:@!ü日本🔑]{1,8}",
+        "[a-z]{200,300}",
+    ]
+    .prop_filter("dot segment", |name| name != "." && name != "..")
+}
+
+/// Paths of one or more key names, with stray and repeated slashes
+fn secret_path() -> impl Strategy<Value = String> {
+    prop::collection::vec((key_name(), "/{1,3}"), 1..6).prop_map(|segments| {
+        segments
+            .into_iter()
+            .map(|(name, sep)| format!("{}{}", sep, name))
+            .collect()
+    })
+}
+
+/// Arbitrary json values, without floats, which don't always round-trip exactly
+fn json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        ".*".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Value::from),
+            prop::collection::hash_map(".*", inner, 0..8)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+fn json_map() -> impl Strategy<Value = Value> {
+    prop::collection::hash_map(".*", json_value(), 0..8)
+        .prop_map(|map| Value::Object(map.into_iter().collect::<Map<_, _>>()))
+}
+
+proptest! {
+    #[test]
+    fn normalize_is_idempotent(path in secret_path()) {
+        let normalized = normalize(&path).unwrap();
+        prop_assert_eq!(normalize(&normalized).unwrap(), normalized.clone());
+        prop_assert!(!normalized.starts_with('/') && !normalized.ends_with('/'));
+        prop_assert!(!normalized.contains("//"));
+    }
+
+    #[test]
+    fn dot_segments_rejected(path in secret_path(), dots in prop_oneof![Just("."), Just("..")]) {
+        let path = format!("{}/{}/x", path, dots);
+        prop_assert!(normalize(&path).is_err());
+    }
+
+    #[test]
+    fn encode_round_trips(path in ".*") {
+        let encoded = encode(&path);
+        prop_assert!(encoded
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-._~/%".contains(&b)));
+        prop_assert_eq!(decode(&encoded), path);
+    }
+
+    #[test]
+    fn compression_round_trips(value in json_map(), zstd in any::<bool>()) {
+        let codec = if zstd { Codec::Zstd } else { Codec::Gzip };
+        let compressed = compress(&value, codec).unwrap();
+        prop_assert_eq!(decompress(compressed).unwrap(), value);
+    }
+
+    #[test]
+    fn mock_store_round_trips(path in secret_path(), value in json_map()) {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let store = MockSecretStore::new("secret");
+        runtime.block_on(async {
+            store.write_secret(&path, &value).await.unwrap();
+            let normalized = normalize(&path).unwrap();
+            prop_assert_eq!(store.read_secret(&normalized).await.unwrap(), value.clone());
+            prop_assert_eq!(store.read_secret(&path).await.unwrap(), value);
+            Ok(())
+        })?;
+    }
+}
@@ -0,0 +1,68 @@
+//! Tests routing of paths to mounts
+//!
+use std::collections::HashMap;
+
+use kv_vault_lib::{config::Config, router::MountRouter};
+
+#[test]
+fn route_longest_prefix() {
+    let router = MountRouter::new(true)
+        .route_prefix("prod", "secret-prod")
+        .unwrap()
+        .route_prefix("/prod/payments/", "payments")
+        .unwrap()
+        .route_prefix("dev", "secret-dev")
+        .unwrap();
+
+    assert_eq!(
+        router.route("prod/app/db").unwrap(),
+        Some(("secret-prod", "app/db".to_string()))
+    );
+    assert_eq!(
+        router.route("/prod/payments/stripe").unwrap(),
+        Some(("payments", "stripe".to_string()))
+    );
+    assert_eq!(
+        router.route("dev//app").unwrap(),
+        Some(("secret-dev", "app".to_string()))
+    );
+    assert_eq!(router.route("production/db").unwrap(), None);
+    assert_eq!(router.route("other").unwrap(), None);
+    assert!(router.route("prod/../dev").is_err());
+}
+
+#[test]
+fn routes_from_config() {
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        (
+            "mount_routes".to_string(),
+            "prod=kv-prod, dev=kv-dev".to_string(),
+        ),
+        ("mount_routes_fallback".to_string(), "false".to_string()),
+    ]);
+    let router = Config::from_values(&values).unwrap().mount_router.unwrap();
+    assert!(!router.fallback);
+    assert_eq!(
+        router.route("dev/app").unwrap(),
+        Some(("kv-dev", "app".to_string()))
+    );
+
+    let values = HashMap::from([
+        ("token".to_string(), "test".to_string()),
+        ("mount_routes".to_string(), "prod".to_string()),
+    ]);
+    assert!(Config::from_values(&values).is_err());
+}
+
+#[test]
+fn route_mounts() {
+    let router = MountRouter::new(false)
+        .route_prefix("prod", "secret-prod")
+        .unwrap()
+        .route_prefix("dev", "secret-dev")
+        .unwrap()
+        .route_prefix("prod/payments", "secret-prod")
+        .unwrap();
+    assert_eq!(router.mounts(), vec!["secret-dev", "secret-prod"]);
+}
@@ -0,0 +1,32 @@
+//! Tests comparison of secrets with their types
+//!
+use kv_vault_lib::schema::field_diff;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Serialize, Deserialize)]
+struct Database {
+    host: String,
+    #[serde(default)]
+    port: u16,
+}
+
+#[test]
+fn diff_fields() {
+    let stored = json!({ "host": "h", "port": 1 });
+    let parsed: Database = serde_json::from_value(stored.clone()).unwrap();
+    assert_eq!(
+        field_diff(&stored, &parsed),
+        (Vec::<String>::new(), Vec::<String>::new())
+    );
+
+    let stored = json!({ "host": "h", "prot": 1, "user": "u" });
+    let parsed: Database = serde_json::from_value(stored.clone()).unwrap();
+    assert_eq!(
+        field_diff(&stored, &parsed),
+        (
+            vec!["prot".to_string(), "user".to_string()],
+            vec!["port".to_string()]
+        )
+    );
+}
@@ -0,0 +1,23 @@
+//! Tests redaction of secret strings
+//!
+use kv_vault_lib::secret::SecretString;
+
+#[test]
+fn secret_string_redacted() {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Credentials {
+        user: String,
+        password: SecretString,
+    }
+
+    let creds = Credentials {
+        user: "app".to_string(),
+        password: SecretString::new("hunter2".to_string()),
+    };
+    let debug = format!("{:?}", creds);
+    assert!(debug.contains("app") && debug.contains("***"));
+    assert!(!debug.contains("hunter2"));
+    assert_eq!(creds.password.to_string(), "***");
+    assert_eq!(creds.password.expose(), "hunter2");
+}
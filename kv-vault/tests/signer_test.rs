@@ -0,0 +1,41 @@
+//! Tests request signing
+//!
+use kv_vault_lib::signer::{HmacSigner, RequestSigner, SIGNATURE_TIMESTAMP_HEADER};
+
+#[test]
+fn hmac_signature() {
+    let signer = HmacSigner::new(b"shared-key");
+    let sig = signer.signature("GET", "/v1/secret/data/app", 1_700_000_000);
+    assert_eq!(sig.len(), 64);
+    assert_eq!(
+        sig,
+        signer.signature("GET", "/v1/secret/data/app", 1_700_000_000)
+    );
+    assert_ne!(
+        sig,
+        signer.signature("POST", "/v1/secret/data/app", 1_700_000_000)
+    );
+    assert_ne!(
+        sig,
+        signer.signature("GET", "/v1/secret/data/app", 1_700_000_001)
+    );
+    assert_ne!(
+        sig,
+        HmacSigner::new(b"other-key").signature("GET", "/v1/secret/data/app", 1_700_000_000)
+    );
+    // key is not included in debug output
+    assert!(!format!("{:?}", signer).contains("shared-key"));
+}
+
+#[test]
+fn hmac_sign_headers() {
+    let signer = HmacSigner::new(b"shared-key");
+    let headers = signer.sign("GET", "/v1/sys/health");
+    let timestamp: u64 = headers
+        .iter()
+        .find(|(name, _)| name == SIGNATURE_TIMESTAMP_HEADER)
+        .map(|(_, value)| value.parse().unwrap())
+        .unwrap();
+    let signature = signer.signature("GET", "/v1/sys/health", timestamp);
+    assert!(headers.iter().any(|(_, value)| *value == signature));
+}
@@ -0,0 +1,53 @@
+//! Tests key status derived from metadata
+//!
+use std::collections::HashMap;
+
+use kv_vault_lib::client::KeyStatus;
+use vaultrs::api::kv2::responses::{ReadSecretMetadataResponse, SecretMetadata};
+
+fn metadata(versions: &[(bool, &str)]) -> ReadSecretMetadataResponse {
+    ReadSecretMetadataResponse {
+        cas_required: false,
+        created_time: String::new(),
+        current_version: versions.len() as u64,
+        delete_version_after: "0s".to_string(),
+        max_versions: 0,
+        oldest_version: 0,
+        updated_time: String::new(),
+        versions: versions
+            .iter()
+            .enumerate()
+            .map(|(i, (destroyed, deletion_time))| {
+                (
+                    (i + 1).to_string(),
+                    SecretMetadata {
+                        created_time: String::new(),
+                        deletion_time: deletion_time.to_string(),
+                        destroyed: *destroyed,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>(),
+    }
+}
+
+#[test]
+fn status_from_metadata() {
+    let deleted = "2023-01-01T00:00:00Z";
+    assert_eq!(
+        KeyStatus::from_metadata(&metadata(&[(false, ""), (false, "")])),
+        KeyStatus::Active
+    );
+    assert_eq!(
+        KeyStatus::from_metadata(&metadata(&[(false, ""), (false, deleted)])),
+        KeyStatus::LatestDeleted
+    );
+    assert_eq!(
+        KeyStatus::from_metadata(&metadata(&[(false, deleted), (true, "")])),
+        KeyStatus::LatestDeleted
+    );
+    assert_eq!(
+        KeyStatus::from_metadata(&metadata(&[(true, ""), (true, "")])),
+        KeyStatus::AllDestroyed
+    );
+}
@@ -0,0 +1,76 @@
+//! Tests the in-memory secret store
+//!
+use kv_vault_lib::{
+    client::WriteKind,
+    error::VaultError,
+    store::{MockSecretStore, SecretStore},
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn mock_read_write_delete() {
+    let store: Box<dyn SecretStore> = Box::new(MockSecretStore::new("secret"));
+
+    assert!(matches!(
+        store.read_secret("app/db").await,
+        Err(VaultError::NotFound { .. })
+    ));
+
+    let meta = store
+        .write_secret("app/db", &json!({ "password": "one" }))
+        .await
+        .unwrap();
+    assert_eq!(meta.version, 1);
+    assert!(meta.is_create());
+    let meta = store
+        .write_secret("app/db", &json!({ "password": "two" }))
+        .await
+        .unwrap();
+    assert_eq!(meta.version, 2);
+    assert_eq!(meta.kind(), WriteKind::Updated);
+    assert_eq!(
+        store.read_secret("app/db").await.unwrap(),
+        json!({ "password": "two" })
+    );
+
+    store.delete_latest("app/db").await.unwrap();
+    assert!(matches!(
+        store.read_secret("app/db").await,
+        Err(VaultError::NotFound { .. })
+    ));
+    assert!(store.delete_latest("app/other").await.is_err());
+}
+
+#[tokio::test]
+async fn mock_rejects_non_map() {
+    let store = MockSecretStore::new("secret");
+    match store.write_secret("app/db", &json!(["a", "b"])).await {
+        Err(VaultError::InvalidData { reason }) => {
+            assert!(reason.contains("an array"), "{}", reason)
+        }
+        other => panic!("expected invalid data, got {:?}", other),
+    }
+    assert!(matches!(
+        store.read_secret("app/db").await,
+        Err(VaultError::NotFound { .. })
+    ));
+}
+
+#[tokio::test]
+async fn mock_list() {
+    let store = MockSecretStore::new("secret");
+    for key in ["app/db", "app/api/key", "app/api/cert", "top"] {
+        store.write_secret(key, &json!({ "v": key })).await.unwrap();
+    }
+
+    assert_eq!(store.list_secrets("").await.unwrap(), vec!["app/", "top"]);
+    assert_eq!(store.list_secrets("app").await.unwrap(), vec!["api/", "db"]);
+    assert_eq!(
+        store.list_secrets("app/api/").await.unwrap(),
+        vec!["cert", "key"]
+    );
+    assert!(matches!(
+        store.list_secrets("missing").await,
+        Err(VaultError::NotFound { .. })
+    ));
+}
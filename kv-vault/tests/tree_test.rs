@@ -0,0 +1,46 @@
+//! Tests conversion between json trees and secret paths
+//!
+use kv_vault_lib::tree::{flatten, insert};
+use serde_json::{json, Value};
+
+#[test]
+fn flatten_tree() {
+    let tree = json!({
+        "app": {
+            "db": { "password": "x", "port": 5432 },
+            "api": { "key": { "value": "k" } },
+        },
+        "top": { "a": "b" },
+    });
+    let secrets = flatten(&tree, "/seed/");
+    assert_eq!(
+        secrets,
+        vec![
+            ("seed/app/api/key".to_string(), json!({ "value": "k" })),
+            (
+                "seed/app/db".to_string(),
+                json!({ "password": "x", "port": 5432 })
+            ),
+            ("seed/top".to_string(), json!({ "a": "b" })),
+        ]
+    );
+    assert_eq!(
+        flatten(&json!({ "a": 1 }), "base"),
+        vec![("base".to_string(), json!({ "a": 1 }))]
+    );
+}
+
+#[test]
+fn insert_round_trip() {
+    let tree = json!({
+        "app": {
+            "db": { "password": "x" },
+            "api": { "key": { "value": "k" } },
+        },
+    });
+    let mut rebuilt = Value::Object(Default::default());
+    for (path, secret) in flatten(&tree, "") {
+        insert(&mut rebuilt, &path, secret);
+    }
+    assert_eq!(rebuilt, tree);
+}